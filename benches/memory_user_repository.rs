@@ -0,0 +1,102 @@
+//! Lookup and insert cost of `MemoryUserRepository` holding 1M users.
+//!
+//! Run with `cargo bench --bench memory_user_repository`. Every call is
+//! driven through `Runtime::block_on`, which adds the same small constant to
+//! each measurement.
+
+use actix_web::rt::Runtime;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use oped_back::core::user::repository::UserRepository;
+use oped_back::infrastructure::user::repository::MemoryUserRepository;
+
+const USERS: i32 = 1_000_000;
+
+/// Spreads consecutive iterations over the whole id range.
+const STRIDE: i32 = 7_919;
+
+fn populated(runtime: &Runtime) -> MemoryUserRepository {
+    let repository = MemoryUserRepository::new();
+
+    runtime.block_on(async {
+        for id in 1..=USERS {
+            repository
+                .insert(format!("user{}", id), "hash".to_owned(), "salt".to_owned())
+                .await
+                .expect("logins are unique");
+        }
+    });
+
+    repository
+}
+
+fn lookups(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("runtime starts");
+
+    let repository = populated(&runtime);
+
+    let mut group = c.benchmark_group("memory_user_repository/1M");
+
+    let mut id = 0;
+
+    group.bench_function("select_one_by_id", |b| {
+        b.iter(|| {
+            id = (id + STRIDE) % USERS;
+
+            runtime
+                .block_on(repository.select_one_by_id(black_box(id + 1)))
+                .expect("user exists")
+        })
+    });
+
+    group.bench_function("select_one_by_login", |b| {
+        b.iter(|| {
+            id = (id + STRIDE) % USERS;
+
+            runtime
+                .block_on(repository.select_one_by_login(black_box(format!("User{}", id + 1))))
+                .expect("user exists")
+        })
+    });
+
+    group.bench_function("select_one_by_login/missing", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(repository.select_one_by_login(black_box("nobody".to_owned())))
+                .expect_err("user does not exist")
+        })
+    });
+
+    let mut next = 0;
+
+    group.bench_function("insert", |b| {
+        b.iter(|| {
+            next += 1;
+
+            runtime
+                .block_on(repository.insert(
+                    black_box(format!("new{}", next)),
+                    "hash".to_owned(),
+                    "salt".to_owned(),
+                ))
+                .expect("login is unique")
+        })
+    });
+
+    group.bench_function("insert/login_already_used", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(repository.insert(
+                    black_box("USER1".to_owned()),
+                    "hash".to_owned(),
+                    "salt".to_owned(),
+                ))
+                .expect_err("login is taken")
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, lookups);
+criterion_main!(benches);
@@ -0,0 +1,524 @@
+use actix_web::main;
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use dotenv::dotenv;
+use std::fs::{read_to_string, write};
+use std::io::{stdin, BufRead, Read};
+use std::path::Path;
+use std::process::exit;
+use std::sync::Arc;
+use validator::Validate;
+
+use oped_back::core::user::{
+    models::{
+        is_valid_role, ImportConflictPolicy, SortOrder, User, UserLoginSearch, UserQuery,
+        UserSortField, MAX_ROLE_LENGTH,
+    },
+    service::UserService,
+};
+use oped_back::infrastructure::{
+    config::{exit_with_config_errors, EnvConfig, StorageBackend},
+    constants::ENV_CONFIG,
+    errors::ApiError,
+    models::JwtData,
+    user::{
+        models::{ImportOutcomeDTO, LoginUserReqDTO, RegisterUserReqDTO, TransferFormatDTO},
+        repository::build_user_repository,
+        service::UserServiceImp,
+        transfer::{export_users, import_users},
+    },
+    utils::generate_salt,
+    validation::field_errors,
+};
+
+const GENERATED_PASSWORD_LENGTH: usize = 20;
+
+fn login_arg() -> Arg {
+    Arg::new("login").required(true).help("Login of the user")
+}
+
+fn role_arg() -> Arg {
+    Arg::new("role")
+        .required(true)
+        .value_parser(parse_role)
+        .help("Role name: lowercase letters, digits, `-` and `_`")
+}
+
+fn password_args(command: Command) -> Command {
+    command
+        .arg(
+            Arg::new("password-stdin")
+                .long("password-stdin")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("generate-password")
+                .help("Read the password from the first line of stdin"),
+        )
+        .arg(
+            Arg::new("generate-password")
+                .long("generate-password")
+                .action(ArgAction::SetTrue)
+                .help("Generate a random password and print it"),
+        )
+}
+
+fn command() -> Command {
+    Command::new("oped-admin")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Manage users of oped-back without going through the HTTP API")
+        .after_help(
+            "The `memory` backend needs `storage.data_dir`, which only one process can use \
+             at a time: stop the server before running oped-admin.",
+        )
+        .subcommand_required(true)
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("PATH")
+                .global(true)
+                .help("TOML configuration file"),
+        )
+        .subcommand(password_args(
+            Command::new("create")
+                .about("Create a user")
+                .arg(login_arg())
+                .arg(
+                    Arg::new("role")
+                        .long("role")
+                        .value_name("ROLE")
+                        .action(ArgAction::Append)
+                        .value_parser(parse_role)
+                        .help("Grant a role right away; repeatable"),
+                ),
+        ))
+        .subcommand(password_args(
+            Command::new("reset-password")
+                .about("Set a new password")
+                .arg(login_arg()),
+        ))
+        .subcommand(
+            Command::new("grant-role")
+                .about("Grant a role")
+                .arg(login_arg())
+                .arg(role_arg()),
+        )
+        .subcommand(
+            Command::new("revoke-role")
+                .about("Revoke a role")
+                .arg(login_arg())
+                .arg(role_arg()),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List users by id")
+                .arg(
+                    Arg::new("search")
+                        .long("search")
+                        .value_name("TEXT")
+                        .help("Only logins containing TEXT"),
+                )
+                .arg(
+                    Arg::new("page")
+                        .long("page")
+                        .value_name("N")
+                        .default_value("1")
+                        .value_parser(value_parser!(u64).range(1..=1_000_000)),
+                )
+                .arg(
+                    Arg::new("per-page")
+                        .long("per-page")
+                        .value_name("N")
+                        .default_value("50")
+                        .value_parser(value_parser!(u64).range(1..=1000)),
+                ),
+        )
+        .subcommand(
+            Command::new("ban")
+                .about("Block logins and end the sessions of a user")
+                .arg(login_arg()),
+        )
+        .subcommand(Command::new("unban").about("Lift a ban").arg(login_arg()))
+        .subcommand(
+            Command::new("import")
+                .about("Import users with their password hashes from a file")
+                .arg(
+                    Arg::new("file")
+                        .required(true)
+                        .help("JSON Lines or CSV file, `-` for stdin"),
+                )
+                .arg(format_arg())
+                .arg(
+                    Arg::new("conflict")
+                        .long("conflict")
+                        .value_parser(["skip", "overwrite", "fail"])
+                        .default_value("fail")
+                        .help("What to do with logins that are already taken"),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .help("Only report what the import would do"),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Export every user with its password hash")
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .help("Write to FILE instead of stdout"),
+                )
+                .arg(format_arg()),
+        )
+        .subcommand(
+            Command::new("verify-token")
+                .about("Check a session token against the configured keys")
+                .arg(
+                    Arg::new("token")
+                        .required(true)
+                        .help("Value of the `jwt` cookie"),
+                ),
+        )
+}
+
+fn format_arg() -> Arg {
+    Arg::new("format")
+        .long("format")
+        .value_parser(["jsonl", "csv"])
+        .help("Defaults to the file extension, then to jsonl")
+}
+
+fn parse_role(role: &str) -> Result<String, String> {
+    if is_valid_role(role) {
+        Ok(role.to_owned())
+    } else {
+        Err(format!(
+            "expected up to {} lowercase letters, digits, `-` or `_`",
+            MAX_ROLE_LENGTH
+        ))
+    }
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("error: {}", message);
+
+    exit(1);
+}
+
+fn read_password(matches: &ArgMatches) -> (String, bool) {
+    if matches.get_flag("generate-password") {
+        return (generate_salt(GENERATED_PASSWORD_LENGTH), true);
+    }
+
+    if matches.get_flag("password-stdin") {
+        let mut line = String::new();
+
+        if let Err(error) = stdin().lock().read_line(&mut line) {
+            fail(format!("can not read the password: {}", error));
+        }
+
+        return (line.trim_end_matches(['\r', '\n']).to_owned(), false);
+    }
+
+    let password = rpassword::prompt_password("Password: ")
+        .unwrap_or_else(|error| fail(format!("can not read the password: {}", error)));
+
+    let confirmation = rpassword::prompt_password("Repeat password: ")
+        .unwrap_or_else(|error| fail(format!("can not read the password: {}", error)));
+
+    if password != confirmation {
+        fail("passwords do not match");
+    }
+
+    (password, false)
+}
+
+fn check(dto: impl Validate) {
+    if let Err(errors) = dto.validate() {
+        for error in field_errors(&errors, ENV_CONFIG.get_default_locale()) {
+            eprintln!("error: {}", error);
+        }
+
+        exit(1);
+    }
+}
+
+fn describe(user: &User) -> String {
+    format!(
+        "{}\t{}\t{}\t{}\t{}",
+        user.get_id(),
+        user.clone_login(),
+        user.get_created_at().to_rfc3339(),
+        user.clone_roles().join(","),
+        if user.is_banned() { "banned" } else { "active" }
+    )
+}
+
+fn transfer_format(matches: &ArgMatches, path: Option<&String>) -> TransferFormatDTO {
+    let format = matches
+        .get_one::<String>("format")
+        .map(String::as_str)
+        .or_else(|| {
+            path.and_then(|path| Path::new(path).extension())
+                .and_then(|extension| extension.to_str())
+        });
+
+    match format {
+        Some("csv") => TransferFormatDTO::Csv,
+        _ => TransferFormatDTO::Jsonl,
+    }
+}
+
+fn login(matches: &ArgMatches) -> String {
+    matches
+        .get_one::<String>("login")
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn role(matches: &ArgMatches) -> String {
+    matches
+        .get_one::<String>("role")
+        .cloned()
+        .unwrap_or_default()
+}
+
+// `false` when the command did part of its work and failed the rest.
+async fn run(user_service: Arc<dyn UserService>, matches: ArgMatches) -> bool {
+    match matches.subcommand() {
+        Some(("create", matches)) => {
+            let (password, generated) = read_password(matches);
+
+            check(RegisterUserReqDTO {
+                login: Some(login(matches)),
+                password: Some(password.clone()),
+            });
+
+            let user = user_service
+                .register(login(matches), password.clone())
+                .await
+                .unwrap_or_else(|error| fail(ApiError::from(error)));
+
+            let roles = matches.get_many::<String>("role").into_iter().flatten();
+
+            for role in roles {
+                user_service
+                    .grant_role(user.clone_login(), role.clone())
+                    .await
+                    .unwrap_or_else(|error| fail(ApiError::from(error)));
+            }
+
+            println!("created user {} `{}`", user.get_id(), user.clone_login());
+
+            if generated {
+                println!("password: {}", password);
+            }
+        }
+        Some(("reset-password", matches)) => {
+            let (password, generated) = read_password(matches);
+
+            check(LoginUserReqDTO {
+                login: Some(login(matches)),
+                password: Some(password.clone()),
+            });
+
+            let user = user_service
+                .reset_password(login(matches), password.clone())
+                .await
+                .unwrap_or_else(|error| fail(ApiError::from(error)));
+
+            println!("password of `{}` reset", user.clone_login());
+
+            if generated {
+                println!("password: {}", password);
+            }
+        }
+        Some(("grant-role", matches)) => {
+            let user = user_service
+                .grant_role(login(matches), role(matches))
+                .await
+                .unwrap_or_else(|error| fail(ApiError::from(error)));
+
+            println!("{}", describe(&user));
+        }
+        Some(("revoke-role", matches)) => {
+            let user = user_service
+                .revoke_role(login(matches), role(matches))
+                .await
+                .unwrap_or_else(|error| fail(ApiError::from(error)));
+
+            println!("{}", describe(&user));
+        }
+        Some(("list", matches)) => {
+            let page = *matches.get_one::<u64>("page").unwrap_or(&1) as usize;
+            let per_page = *matches.get_one::<u64>("per-page").unwrap_or(&50) as usize;
+
+            let search = matches
+                .get_one::<String>("search")
+                .map(|search| UserLoginSearch::Substring(search.clone()));
+
+            let query = UserQuery::new(
+                search,
+                UserSortField::Id,
+                SortOrder::Asc,
+                (page - 1) * per_page,
+                per_page,
+            );
+
+            let users = user_service
+                .get_many(query)
+                .await
+                .unwrap_or_else(|error| fail(ApiError::from(error)));
+
+            let total = users.get_total();
+
+            println!("id\tlogin\tcreated_at\troles\tstatus");
+
+            for user in users.into_users().into_users() {
+                println!("{}", describe(&user));
+            }
+
+            eprintln!("page {} of {} users", page, total);
+        }
+        Some((name @ ("ban" | "unban"), matches)) => {
+            let user = user_service
+                .set_banned(login(matches), name == "ban")
+                .await
+                .unwrap_or_else(|error| fail(ApiError::from(error)));
+
+            println!("{}", describe(&user));
+        }
+        Some(("import", matches)) => {
+            let path = matches.get_one::<String>("file");
+
+            let input = match path.map(String::as_str) {
+                Some("-") | None => {
+                    let mut input = String::new();
+
+                    stdin()
+                        .read_to_string(&mut input)
+                        .map(|_| input)
+                        .map_err(|error| error.to_string())
+                }
+                Some(path) => read_to_string(path).map_err(|error| error.to_string()),
+            }
+            .unwrap_or_else(|error| fail(format!("can not read the input: {}", error)));
+
+            let conflict = match matches.get_one::<String>("conflict").map(String::as_str) {
+                Some("skip") => ImportConflictPolicy::Skip,
+                Some("overwrite") => ImportConflictPolicy::Overwrite,
+                _ => ImportConflictPolicy::Fail,
+            };
+
+            let dry_run = matches.get_flag("dry-run");
+
+            let report = import_users(
+                user_service.as_ref(),
+                transfer_format(matches, path),
+                input.as_str(),
+                conflict,
+                dry_run,
+                ENV_CONFIG.get_default_locale(),
+            )
+            .await;
+
+            for row in report.rows() {
+                if row.get_outcome() != ImportOutcomeDTO::Created {
+                    eprintln!("{}", row);
+                }
+            }
+
+            println!("{}", report);
+
+            if !report.is_applied() {
+                println!("nothing was written");
+            }
+
+            return report.get_failed() == 0;
+        }
+        Some(("export", matches)) => {
+            let path = matches.get_one::<String>("output");
+
+            let output = export_users(user_service.as_ref(), transfer_format(matches, path))
+                .await
+                .unwrap_or_else(|error| fail(ApiError::from(error)));
+
+            match path {
+                Some(path) => write(path, output)
+                    .unwrap_or_else(|error| fail(format!("can not write {}: {}", path, error))),
+                None => print!("{}", output),
+            }
+        }
+        Some(("verify-token", matches)) => {
+            let token = matches
+                .get_one::<String>("token")
+                .cloned()
+                .unwrap_or_default();
+
+            let jwt_data = JwtData::from_token_str(token.as_str())
+                .unwrap_or_else(|_| fail("invalid token: bad signature, unknown key or malformed"));
+
+            println!("valid token for user id {}", jwt_data.get_user_id());
+
+            match user_service.get_one_by_id(jwt_data.get_user_id()).await {
+                Ok(user) => println!("{}", describe(&user)),
+                Err(error) => fail(ApiError::from(error)),
+            }
+        }
+        _ => unreachable!("clap requires a subcommand"),
+    }
+
+    true
+}
+
+#[main]
+async fn main() {
+    dotenv().ok();
+
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(tracing::Level::WARN)
+        .init();
+
+    let matches = command().get_matches();
+
+    let mut config_args = vec!["oped-admin".to_owned()];
+
+    if let Some(path) = matches.get_one::<String>("config") {
+        config_args.extend(["--config".to_owned(), path.clone()]);
+    }
+
+    match EnvConfig::load(config_args) {
+        Ok(config) => ENV_CONFIG.init(config),
+        Err(errors) => exit_with_config_errors(errors),
+    }
+
+    if let Err(errors) = ENV_CONFIG.check() {
+        exit_with_config_errors(errors);
+    }
+
+    if ENV_CONFIG.get_storage_backend() == StorageBackend::Memory
+        && ENV_CONFIG.clone_storage_data_dir().is_empty()
+    {
+        fail("the `memory` backend keeps no users without `storage.data_dir`");
+    }
+
+    let user_repository = build_user_repository(&ENV_CONFIG)
+        .unwrap_or_else(|message| fail(format!("can not open user storage: {}", message)));
+
+    let user_service: Arc<dyn UserService> = Arc::new(UserServiceImp::new(
+        user_repository.clone(),
+        ENV_CONFIG.get_login_policy(),
+    ));
+
+    let succeeded = run(user_service, matches).await;
+
+    if let Err(error) = user_repository.flush().await {
+        fail(format!("can not flush user storage: {:?}", error));
+    }
+
+    if !succeeded {
+        exit(1);
+    }
+}
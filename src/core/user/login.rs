@@ -0,0 +1,84 @@
+use std::str::FromStr;
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_security::{skeleton, GeneralSecurityProfile, MixedScript};
+
+const LOGIN_PUNCTUATION: &[char] = &['_', '-', '.'];
+
+pub const LOGIN_MIN_LENGTH: usize = 3;
+pub const LOGIN_MAX_LENGTH: usize = 30;
+
+pub fn normalize_login(login: &str) -> String {
+    login.nfkc().collect()
+}
+
+pub fn fold_login(login: &str) -> String {
+    normalize_login(login).to_lowercase().nfkc().collect()
+}
+
+// "Alice", "alice" and "аlice" (Cyrillic "а") share one key.
+pub fn login_key(login: &str) -> String {
+    skeleton(fold_login(login).as_str()).collect()
+}
+
+pub fn check_login_length(login: &str) -> Result<(), LoginPolicyError> {
+    let length = login.chars().count();
+
+    if !(LOGIN_MIN_LENGTH..=LOGIN_MAX_LENGTH).contains(&length) {
+        return Err(LoginPolicyError::Length);
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginPolicyError {
+    Length,
+    NotAllowedCharacter,
+    MixedScript,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginPolicy {
+    Ascii,
+    Unicode,
+}
+
+impl LoginPolicy {
+    // Expects a normalized login: normalization can lengthen it, e.g. "ﬃ"
+    // becomes "ffi".
+    pub fn check(&self, login: &str) -> Result<(), LoginPolicyError> {
+        check_login_length(login)?;
+
+        let allowed = match self {
+            Self::Ascii => login
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || LOGIN_PUNCTUATION.contains(&c)),
+            Self::Unicode => login
+                .chars()
+                .all(|c| c.identifier_allowed() || LOGIN_PUNCTUATION.contains(&c)),
+        };
+
+        if !allowed {
+            return Err(LoginPolicyError::NotAllowedCharacter);
+        }
+
+        if !login.is_single_script() {
+            return Err(LoginPolicyError::MixedScript);
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for LoginPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ascii" => Ok(Self::Ascii),
+            "unicode" => Ok(Self::Unicode),
+            _ => Err(()),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+pub mod login;
 pub mod models;
 pub mod repository;
 pub mod service;
@@ -1,19 +1,74 @@
+use chrono::{DateTime, Utc};
+
+use super::login::fold_login;
+
+pub const MAX_ROLE_LENGTH: usize = 32;
+
+pub fn is_valid_role(role: &str) -> bool {
+    !role.is_empty()
+        && role.len() <= MAX_ROLE_LENGTH
+        && role
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+// Imported users keep the scheme of their old system until they get a new
+// password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordAlgorithm {
+    Sha256,
+    Bcrypt,
+}
+
 #[derive(Debug, Clone)]
 pub struct User {
     id: i32,
     login: String,
+    algorithm: PasswordAlgorithm,
     hash: String,
     salt: String,
+    created_at: DateTime<Utc>,
+    roles: Vec<String>,
+    banned: bool,
 }
 
 impl User {
-    pub fn new(id: i32, login: String, hash: String, salt: String) -> Self {
+    pub fn new(
+        id: i32,
+        login: String,
+        hash: String,
+        salt: String,
+        created_at: DateTime<Utc>,
+    ) -> Self {
         Self {
             id,
             login,
+            algorithm: PasswordAlgorithm::Sha256,
             hash,
             salt,
+            created_at,
+            roles: vec![],
+            banned: false,
+        }
+    }
+
+    pub fn from_record(id: i32, record: UserRecord) -> Self {
+        let mut user = Self {
+            id,
+            login: record.login,
+            algorithm: record.algorithm,
+            hash: record.hash,
+            salt: record.salt,
+            created_at: record.created_at,
+            roles: vec![],
+            banned: record.banned,
+        };
+
+        for role in record.roles {
+            user.grant_role(role);
         }
+
+        user
     }
 
     pub fn get_id(&self) -> i32 {
@@ -24,6 +79,10 @@ impl User {
         self.login.clone()
     }
 
+    pub fn get_password_algorithm(&self) -> PasswordAlgorithm {
+        self.algorithm
+    }
+
     pub fn clone_salt(&self) -> String {
         self.salt.clone()
     }
@@ -31,6 +90,90 @@ impl User {
     pub fn clone_hash(&self) -> String {
         self.hash.clone()
     }
+
+    pub fn get_created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    pub fn clone_roles(&self) -> Vec<String> {
+        self.roles.clone()
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|granted| granted == role)
+    }
+
+    pub fn is_banned(&self) -> bool {
+        self.banned
+    }
+
+    pub fn set_password(&mut self, algorithm: PasswordAlgorithm, hash: String, salt: String) {
+        self.algorithm = algorithm;
+        self.hash = hash;
+        self.salt = salt;
+    }
+
+    pub fn grant_role(&mut self, role: String) {
+        if let Err(index) = self.roles.binary_search(&role) {
+            self.roles.insert(index, role);
+        }
+    }
+
+    pub fn revoke_role(&mut self, role: &str) {
+        self.roles.retain(|granted| granted != role);
+    }
+
+    pub fn set_banned(&mut self, banned: bool) {
+        self.banned = banned;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UserRecord {
+    login: String,
+    algorithm: PasswordAlgorithm,
+    hash: String,
+    salt: String,
+    roles: Vec<String>,
+    created_at: DateTime<Utc>,
+    banned: bool,
+}
+
+impl UserRecord {
+    pub fn new(
+        login: String,
+        algorithm: PasswordAlgorithm,
+        hash: String,
+        salt: String,
+        roles: Vec<String>,
+        created_at: DateTime<Utc>,
+        banned: bool,
+    ) -> Self {
+        Self {
+            login,
+            algorithm,
+            hash,
+            salt,
+            roles,
+            created_at,
+            banned,
+        }
+    }
+
+    pub fn clone_login(&self) -> String {
+        self.login.clone()
+    }
+
+    pub fn set_login(&mut self, login: String) {
+        self.login = login;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportConflictPolicy {
+    Skip,
+    Overwrite,
+    Fail,
 }
 
 #[derive(Debug, Clone)]
@@ -45,3 +188,100 @@ impl Users {
         self.0
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserSortField {
+    Id,
+    Login,
+    CreatedAt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UserLoginSearch {
+    Prefix(String),
+    Substring(String),
+}
+
+impl UserLoginSearch {
+    pub fn matches(&self, login: &str) -> bool {
+        let login = fold_login(login);
+
+        match self {
+            Self::Prefix(prefix) => login.starts_with(fold_login(prefix).as_str()),
+            Self::Substring(substring) => login.contains(fold_login(substring).as_str()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UserQuery {
+    search: Option<UserLoginSearch>,
+    sort_field: UserSortField,
+    sort_order: SortOrder,
+    offset: usize,
+    limit: usize,
+}
+
+impl UserQuery {
+    pub fn new(
+        search: Option<UserLoginSearch>,
+        sort_field: UserSortField,
+        sort_order: SortOrder,
+        offset: usize,
+        limit: usize,
+    ) -> Self {
+        Self {
+            search,
+            sort_field,
+            sort_order,
+            offset,
+            limit,
+        }
+    }
+
+    pub fn get_search(&self) -> Option<&UserLoginSearch> {
+        self.search.as_ref()
+    }
+
+    pub fn get_sort_field(&self) -> UserSortField {
+        self.sort_field
+    }
+
+    pub fn get_sort_order(&self) -> SortOrder {
+        self.sort_order
+    }
+
+    pub fn get_offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn get_limit(&self) -> usize {
+        self.limit
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UsersPage {
+    users: Users,
+    total: usize,
+}
+
+impl UsersPage {
+    pub fn new(users: Users, total: usize) -> Self {
+        Self { users, total }
+    }
+
+    pub fn get_total(&self) -> usize {
+        self.total
+    }
+
+    pub fn into_users(self) -> Users {
+        self.users
+    }
+}
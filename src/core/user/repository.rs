@@ -1,27 +1,48 @@
 use async_trait::async_trait;
 
-use super::models::User;
+use crate::core::error::ErrorSource;
+
+use super::models::{User, UserQuery, UserRecord, UsersPage};
 
 #[derive(Debug, Clone)]
-pub enum UserRepositorySelectAllError {
-    UnexpectedError,
+pub enum UserRepositorySelectManyError {
+    UnexpectedError(ErrorSource),
 }
 
 #[derive(Debug, Clone)]
 pub enum UserRepositorySelectOneError {
     NotFound,
-    UnexpectedError,
+    UnexpectedError(ErrorSource),
 }
 
 #[derive(Debug, Clone)]
 pub enum UserRepositoryInsertError {
     LoginAlreadyUsed,
-    UnexpectedError,
+    UnexpectedError(ErrorSource),
+}
+
+#[derive(Debug, Clone)]
+pub enum UserRepositoryUpdateError {
+    NotFound,
+    UnexpectedError(ErrorSource),
+}
+
+#[derive(Debug, Clone)]
+pub enum UserRepositoryHealthCheckError {
+    Unavailable(ErrorSource),
+}
+
+#[derive(Debug, Clone)]
+pub enum UserRepositoryFlushError {
+    UnexpectedError(ErrorSource),
 }
 
 #[async_trait]
 pub trait UserRepository: Send + Sync {
-    async fn select_all(&self) -> Result<Vec<User>, UserRepositorySelectAllError>;
+    async fn select_many(
+        &self,
+        query: UserQuery,
+    ) -> Result<UsersPage, UserRepositorySelectManyError>;
     async fn select_one_by_id(&self, id: i32) -> Result<User, UserRepositorySelectOneError>;
     async fn select_one_by_login(
         &self,
@@ -33,4 +54,8 @@ pub trait UserRepository: Send + Sync {
         hash: String,
         salt: String,
     ) -> Result<i32, UserRepositoryInsertError>;
+    async fn insert_record(&self, record: UserRecord) -> Result<i32, UserRepositoryInsertError>;
+    async fn update(&self, user: User) -> Result<(), UserRepositoryUpdateError>;
+    async fn health_check(&self) -> Result<(), UserRepositoryHealthCheckError>;
+    async fn flush(&self) -> Result<(), UserRepositoryFlushError>;
 }
@@ -1,21 +1,25 @@
 use async_trait::async_trait;
 
+use crate::core::error::ErrorSource;
+
 use super::{
-    models::{User, Users},
+    login::LoginPolicyError,
+    models::{ImportConflictPolicy, User, UserQuery, UserRecord, UsersPage},
     repository::{
-        UserRepositoryInsertError, UserRepositorySelectAllError, UserRepositorySelectOneError,
+        UserRepositoryInsertError, UserRepositorySelectManyError, UserRepositorySelectOneError,
+        UserRepositoryUpdateError,
     },
 };
 
 #[derive(Debug, Clone)]
-pub enum UserServiceGetAllError {
-    UnexpectedError,
+pub enum UserServiceGetManyError {
+    UnexpectedError(ErrorSource),
 }
 
-impl Into<UserServiceGetAllError> for UserRepositorySelectAllError {
-    fn into(self) -> UserServiceGetAllError {
-        match self {
-            Self::UnexpectedError => UserServiceGetAllError::UnexpectedError,
+impl From<UserRepositorySelectManyError> for UserServiceGetManyError {
+    fn from(error: UserRepositorySelectManyError) -> Self {
+        match error {
+            UserRepositorySelectManyError::UnexpectedError(source) => Self::UnexpectedError(source),
         }
     }
 }
@@ -23,14 +27,14 @@ impl Into<UserServiceGetAllError> for UserRepositorySelectAllError {
 #[derive(Debug, Clone)]
 pub enum UserServiceGetOneError {
     NotFound,
-    UnexpectedError,
+    UnexpectedError(ErrorSource),
 }
 
-impl Into<UserServiceGetOneError> for UserRepositorySelectOneError {
-    fn into(self) -> UserServiceGetOneError {
-        match self {
-            Self::NotFound => UserServiceGetOneError::NotFound,
-            Self::UnexpectedError => UserServiceGetOneError::UnexpectedError,
+impl From<UserRepositorySelectOneError> for UserServiceGetOneError {
+    fn from(error: UserRepositorySelectOneError) -> Self {
+        match error {
+            UserRepositorySelectOneError::NotFound => Self::NotFound,
+            UserRepositorySelectOneError::UnexpectedError(source) => Self::UnexpectedError(source),
         }
     }
 }
@@ -38,22 +42,38 @@ impl Into<UserServiceGetOneError> for UserRepositorySelectOneError {
 #[derive(Debug, Clone)]
 pub enum UserServiceRegisterError {
     LoginAlreadyUsed,
-    UnexpectedError,
+    LoginLength,
+    LoginNotAllowed,
+    LoginMixedScript,
+    UnexpectedError(ErrorSource),
 }
 
-impl Into<UserServiceRegisterError> for UserRepositoryInsertError {
-    fn into(self) -> UserServiceRegisterError {
-        match self {
-            Self::LoginAlreadyUsed => UserServiceRegisterError::LoginAlreadyUsed,
-            Self::UnexpectedError => UserServiceRegisterError::UnexpectedError,
+impl From<LoginPolicyError> for UserServiceRegisterError {
+    fn from(error: LoginPolicyError) -> Self {
+        match error {
+            LoginPolicyError::Length => Self::LoginLength,
+            LoginPolicyError::NotAllowedCharacter => Self::LoginNotAllowed,
+            LoginPolicyError::MixedScript => Self::LoginMixedScript,
         }
     }
 }
 
-impl Into<UserServiceRegisterError> for UserRepositorySelectOneError {
-    fn into(self) -> UserServiceRegisterError {
-        match self {
-            Self::NotFound | Self::UnexpectedError => UserServiceRegisterError::UnexpectedError,
+impl From<UserRepositoryInsertError> for UserServiceRegisterError {
+    fn from(error: UserRepositoryInsertError) -> Self {
+        match error {
+            UserRepositoryInsertError::LoginAlreadyUsed => Self::LoginAlreadyUsed,
+            UserRepositoryInsertError::UnexpectedError(source) => Self::UnexpectedError(source),
+        }
+    }
+}
+
+impl From<UserRepositorySelectOneError> for UserServiceRegisterError {
+    fn from(error: UserRepositorySelectOneError) -> Self {
+        match error {
+            UserRepositorySelectOneError::NotFound => {
+                Self::UnexpectedError(ErrorSource::new("registered user is not found"))
+            }
+            UserRepositorySelectOneError::UnexpectedError(source) => Self::UnexpectedError(source),
         }
     }
 }
@@ -62,21 +82,116 @@ impl Into<UserServiceRegisterError> for UserRepositorySelectOneError {
 pub enum UserServiceLoginError {
     NotFound,
     WrongPassword,
-    UnexpectedError,
+    Banned,
+    UnexpectedError(ErrorSource),
+}
+
+impl From<UserRepositorySelectOneError> for UserServiceLoginError {
+    fn from(error: UserRepositorySelectOneError) -> Self {
+        match error {
+            UserRepositorySelectOneError::UnexpectedError(source) => Self::UnexpectedError(source),
+            UserRepositorySelectOneError::NotFound => Self::NotFound,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum UserServiceUpdateError {
+    NotFound,
+    UnexpectedError(ErrorSource),
+}
+
+impl From<UserRepositorySelectOneError> for UserServiceUpdateError {
+    fn from(error: UserRepositorySelectOneError) -> Self {
+        match error {
+            UserRepositorySelectOneError::NotFound => Self::NotFound,
+            UserRepositorySelectOneError::UnexpectedError(source) => Self::UnexpectedError(source),
+        }
+    }
+}
+
+impl From<UserRepositoryUpdateError> for UserServiceUpdateError {
+    fn from(error: UserRepositoryUpdateError) -> Self {
+        match error {
+            UserRepositoryUpdateError::NotFound => Self::NotFound,
+            UserRepositoryUpdateError::UnexpectedError(source) => Self::UnexpectedError(source),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum UserServiceImportError {
+    LoginAlreadyUsed,
+    LoginRepeated,
+    LoginLength,
+    LoginNotAllowed,
+    LoginMixedScript,
+    UnexpectedError(ErrorSource),
+}
+
+impl From<LoginPolicyError> for UserServiceImportError {
+    fn from(error: LoginPolicyError) -> Self {
+        match error {
+            LoginPolicyError::Length => Self::LoginLength,
+            LoginPolicyError::NotAllowedCharacter => Self::LoginNotAllowed,
+            LoginPolicyError::MixedScript => Self::LoginMixedScript,
+        }
+    }
+}
+
+impl From<UserRepositoryInsertError> for UserServiceImportError {
+    fn from(error: UserRepositoryInsertError) -> Self {
+        match error {
+            UserRepositoryInsertError::LoginAlreadyUsed => Self::LoginAlreadyUsed,
+            UserRepositoryInsertError::UnexpectedError(source) => Self::UnexpectedError(source),
+        }
+    }
 }
 
-impl Into<UserServiceLoginError> for UserRepositorySelectOneError {
-    fn into(self) -> UserServiceLoginError {
-        match self {
-            Self::UnexpectedError => UserServiceLoginError::UnexpectedError,
-            Self::NotFound => UserServiceLoginError::NotFound,
+impl From<UserRepositoryUpdateError> for UserServiceImportError {
+    fn from(error: UserRepositoryUpdateError) -> Self {
+        match error {
+            UserRepositoryUpdateError::NotFound => {
+                Self::UnexpectedError(ErrorSource::new("overwritten user is not found"))
+            }
+            UserRepositoryUpdateError::UnexpectedError(source) => Self::UnexpectedError(source),
         }
     }
 }
 
+#[derive(Debug, Clone)]
+pub enum ImportOutcome {
+    Created,
+    Overwritten,
+    Skipped,
+    Failed(UserServiceImportError),
+}
+
+// When nothing was `applied` (a dry run, or a conflict under `Fail`) the
+// outcomes are what would have happened.
+#[derive(Debug, Clone)]
+pub struct ImportResult {
+    outcomes: Vec<ImportOutcome>,
+    applied: bool,
+}
+
+impl ImportResult {
+    pub fn new(outcomes: Vec<ImportOutcome>, applied: bool) -> Self {
+        Self { outcomes, applied }
+    }
+
+    pub fn is_applied(&self) -> bool {
+        self.applied
+    }
+
+    pub fn into_outcomes(self) -> Vec<ImportOutcome> {
+        self.outcomes
+    }
+}
+
 #[async_trait]
 pub trait UserService: Sync + Send {
-    async fn get_all(&self) -> Result<Users, UserServiceGetAllError>;
+    async fn get_many(&self, query: UserQuery) -> Result<UsersPage, UserServiceGetManyError>;
     async fn get_one_by_id(&self, id: i32) -> Result<User, UserServiceGetOneError>;
     async fn get_one_by_login(&self, login: String) -> Result<User, UserServiceGetOneError>;
     async fn register(
@@ -86,4 +201,24 @@ pub trait UserService: Sync + Send {
     ) -> Result<User, UserServiceRegisterError>;
     async fn login(&self, login: String, password: String)
         -> Result<String, UserServiceLoginError>;
+    async fn reset_password(
+        &self,
+        login: String,
+        password: String,
+    ) -> Result<User, UserServiceUpdateError>;
+    async fn grant_role(&self, login: String, role: String)
+        -> Result<User, UserServiceUpdateError>;
+    async fn revoke_role(
+        &self,
+        login: String,
+        role: String,
+    ) -> Result<User, UserServiceUpdateError>;
+    async fn set_banned(&self, login: String, banned: bool)
+        -> Result<User, UserServiceUpdateError>;
+    async fn import(
+        &self,
+        records: Vec<UserRecord>,
+        conflict: ImportConflictPolicy,
+        dry_run: bool,
+    ) -> ImportResult;
 }
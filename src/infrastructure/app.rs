@@ -0,0 +1,52 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    middleware::Condition,
+    web::{Data, JsonConfig, QueryConfig},
+    App, Error,
+};
+use std::sync::Arc;
+
+use crate::core::user::{repository::UserRepository, service::UserService};
+
+use super::{
+    constants::ENV_CONFIG, controllers::configure, cors::Cors, errors::ApiError,
+    i18n::Localization, metrics::Metrics, request_id::RequestTracing, shutdown::InFlightRequests,
+    tls::HttpsRedirect,
+};
+
+// `ENV_CONFIG` must be initialized first.
+pub fn build_app(
+    user_repository: Arc<dyn UserRepository>,
+    user_service: Arc<dyn UserService>,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    let json_config = JsonConfig::default()
+        .error_handler(|err, _req| ApiError::MalformedRequest(err.to_string()).into());
+
+    let query_config = QueryConfig::default()
+        .error_handler(|err, _req| ApiError::MalformedRequest(err.to_string()).into());
+
+    App::new()
+        .app_data(json_config)
+        .app_data(query_config)
+        .app_data(Data::from(user_repository))
+        .app_data(Data::from(user_service))
+        .configure(configure)
+        .wrap(Localization::default())
+        .wrap(Cors::default())
+        .wrap(Metrics::default())
+        .wrap(RequestTracing::default())
+        .wrap(InFlightRequests::default())
+        .wrap(Condition::new(
+            ENV_CONFIG.get_tls_redirect_port().is_some(),
+            HttpsRedirect::default(),
+        ))
+}
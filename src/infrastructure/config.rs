@@ -0,0 +1,1290 @@
+use actix_web::cookie::SameSite;
+use actix_web::http::{
+    header::{HeaderName, HeaderValue},
+    Method, Uri,
+};
+use clap::{error::ErrorKind, Arg, ArgMatches, Command};
+use std::collections::HashMap;
+use std::env::var;
+use std::ffi::OsString;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs::read_to_string;
+use std::path::Path;
+use std::process::exit;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+use toml::{Table, Value};
+use tracing_subscriber::EnvFilter;
+
+use crate::core::user::login::LoginPolicy;
+
+use super::i18n::Locale;
+use super::logging::LogFormat;
+use super::signing::{JwtAlgorithm, KeyRing};
+use super::telemetry::TraceExporter;
+use super::tls::load_certified_key;
+
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+struct Setting {
+    key: &'static str,
+    env: &'static str,
+    flag: Option<&'static str>,
+    default: Option<&'static str>,
+    secret: bool,
+    help: &'static str,
+}
+
+const SETTINGS: &[Setting] = &[
+    Setting {
+        key: "server.host",
+        env: "HOST",
+        flag: Some("host"),
+        default: Some("127.0.0.1"),
+        secret: false,
+        help: "Address to bind the HTTP server to",
+    },
+    Setting {
+        key: "server.port",
+        env: "PORT",
+        flag: Some("port"),
+        default: Some("25565"),
+        secret: false,
+        help: "Port to bind the HTTP server to",
+    },
+    Setting {
+        key: "server.workers",
+        env: "WORKERS",
+        flag: Some("workers"),
+        default: None,
+        secret: false,
+        help: "Number of worker threads (defaults to the number of CPUs)",
+    },
+    Setting {
+        key: "server.shutdown_delay",
+        env: "SHUTDOWN_DELAY",
+        flag: Some("shutdown-delay"),
+        default: Some("5"),
+        secret: false,
+        help: "Seconds `/readyz` reports not ready after a shutdown signal before connections stop being accepted",
+    },
+    Setting {
+        key: "server.shutdown_timeout",
+        env: "SHUTDOWN_TIMEOUT",
+        flag: Some("shutdown-timeout"),
+        default: Some("30"),
+        secret: false,
+        help: "Seconds in-flight requests get to finish before they are dropped",
+    },
+    Setting {
+        key: "log.level",
+        env: "LOG_LEVEL",
+        flag: Some("log-level"),
+        default: Some("info"),
+        secret: false,
+        help: "Log level or filter directives, e.g. `info` or `warn,oped_back=debug`",
+    },
+    Setting {
+        key: "log.format",
+        env: "LOG_FORMAT",
+        flag: Some("log-format"),
+        default: Some("json"),
+        secret: false,
+        help: "Log line format: `json` or `text`",
+    },
+    Setting {
+        key: "tracing.exporter",
+        env: "TRACING_EXPORTER",
+        flag: Some("tracing-exporter"),
+        default: Some("none"),
+        secret: false,
+        help: "Where finished spans go: `none`, `otlp` or `stdout`",
+    },
+    Setting {
+        key: "tracing.otlp_endpoint",
+        env: "TRACING_OTLP_ENDPOINT",
+        flag: Some("tracing-otlp-endpoint"),
+        default: Some("http://localhost:4318/v1/traces"),
+        secret: false,
+        help: "OTLP/HTTP traces URL of the collector",
+    },
+    Setting {
+        key: "tracing.service_name",
+        env: "TRACING_SERVICE_NAME",
+        flag: Some("tracing-service-name"),
+        default: Some("oped-back"),
+        secret: false,
+        help: "`service.name` reported with every span",
+    },
+    Setting {
+        key: "metrics.token",
+        env: "METRICS_TOKEN",
+        flag: None,
+        default: None,
+        secret: true,
+        help: "Bearer token `/metrics` requires; the endpoint is not served without one",
+    },
+    Setting {
+        key: "tls.cert_file",
+        env: "TLS_CERT_FILE",
+        flag: Some("tls-cert-file"),
+        default: None,
+        secret: false,
+        help: "PEM certificate chain; serves HTTPS on `server.port` when set with `tls.key_file`",
+    },
+    Setting {
+        key: "tls.key_file",
+        env: "TLS_KEY_FILE",
+        flag: Some("tls-key-file"),
+        default: None,
+        secret: false,
+        help: "PEM private key of `tls.cert_file`",
+    },
+    Setting {
+        key: "tls.redirect_port",
+        env: "TLS_REDIRECT_PORT",
+        flag: Some("tls-redirect-port"),
+        default: None,
+        secret: false,
+        help: "Plain HTTP port redirecting to HTTPS",
+    },
+    Setting {
+        key: "storage.backend",
+        env: "STORAGE_BACKEND",
+        flag: Some("storage-backend"),
+        default: Some("memory"),
+        secret: false,
+        help: "User storage backend: `memory`",
+    },
+    Setting {
+        key: "storage.data_dir",
+        env: "STORAGE_DATA_DIR",
+        flag: Some("storage-data-dir"),
+        default: None,
+        secret: false,
+        help: "Directory the `memory` backend snapshots users to; nothing is persisted when unset",
+    },
+    Setting {
+        key: "storage.snapshot_interval",
+        env: "STORAGE_SNAPSHOT_INTERVAL",
+        flag: Some("storage-snapshot-interval"),
+        default: Some("300"),
+        secret: false,
+        help: "Seconds between snapshots of `storage.data_dir`, `0` for on shutdown only",
+    },
+    Setting {
+        key: "storage.cache_ttl",
+        env: "STORAGE_CACHE_TTL",
+        flag: Some("storage-cache-ttl"),
+        default: Some("30"),
+        secret: false,
+        help: "Seconds a user looked up by id or login is served from memory, `0` to disable the cache",
+    },
+    Setting {
+        key: "storage.cache_capacity",
+        env: "STORAGE_CACHE_CAPACITY",
+        flag: Some("storage-cache-capacity"),
+        default: Some("10000"),
+        secret: false,
+        help: "Most users the lookup cache holds, `0` to disable it",
+    },
+    Setting {
+        key: "jwt.secret",
+        env: "JWT_SECRET",
+        flag: None,
+        default: None,
+        secret: true,
+        help: "Secret used to sign HS256 session tokens",
+    },
+    Setting {
+        key: "jwt.algorithm",
+        env: "JWT_ALGORITHM",
+        flag: Some("jwt-algorithm"),
+        default: Some("hs256"),
+        secret: false,
+        help: "Session token signature: `hs256`, `rs256` or `eddsa`",
+    },
+    Setting {
+        key: "jwt.private_key_file",
+        env: "JWT_PRIVATE_KEY_FILE",
+        flag: Some("jwt-private-key-file"),
+        default: None,
+        secret: false,
+        help: "PEM private key used to sign RS256 or EdDSA session tokens",
+    },
+    Setting {
+        key: "jwt.key_id",
+        env: "JWT_KEY_ID",
+        flag: Some("jwt-key-id"),
+        default: Some("default"),
+        secret: false,
+        help: "Key id (`kid`) of the current key, written into new session tokens",
+    },
+    Setting {
+        key: "jwt.previous_keys",
+        env: "JWT_PREVIOUS_KEYS",
+        flag: None,
+        default: Some(""),
+        secret: true,
+        help: "Retired HS256 keys still accepted for verification: one `kid:secret` per line",
+    },
+    Setting {
+        key: "jwt.previous_public_keys",
+        env: "JWT_PREVIOUS_PUBLIC_KEYS",
+        flag: Some("jwt-previous-public-keys"),
+        default: Some(""),
+        secret: false,
+        help: "Retired RS256/EdDSA keys still accepted and published: `kid:key.pem,kid:key.pem`",
+    },
+    Setting {
+        key: "jwt.ttl",
+        env: "JWT_TTL",
+        flag: Some("jwt-ttl"),
+        default: Some("86400"),
+        secret: false,
+        help: "Seconds a session token and its cookie stay valid",
+    },
+    Setting {
+        key: "jwt.domain",
+        env: "JWT_DOMAIN",
+        flag: Some("jwt-domain"),
+        default: None,
+        secret: false,
+        help: "Domain of the session cookie",
+    },
+    Setting {
+        key: "jwt.same_site",
+        env: "JWT_SAME_SITE",
+        flag: Some("jwt-same-site"),
+        default: Some("lax"),
+        secret: false,
+        help: "`SameSite` attribute of the session cookie: `strict`, `lax` or `none`",
+    },
+    Setting {
+        key: "cors.allow_origin",
+        env: "ACCESS_CONTROL_ALLOW_ORIGIN",
+        flag: Some("cors-allow-origin"),
+        default: None,
+        secret: false,
+        help: "Allowed origins, comma separated: `https://app.example.com`, `https://*.example.com` or `*`",
+    },
+    Setting {
+        key: "cors.allow_methods",
+        env: "ACCESS_CONTROL_ALLOW_METHODS",
+        flag: Some("cors-allow-methods"),
+        default: None,
+        secret: false,
+        help: "Value of `Access-Control-Allow-Methods`",
+    },
+    Setting {
+        key: "cors.allow_headers",
+        env: "ACCESS_CONTROL_ALLOW_HEADERS",
+        flag: Some("cors-allow-headers"),
+        default: None,
+        secret: false,
+        help: "Value of `Access-Control-Allow-Headers`",
+    },
+    Setting {
+        key: "cors.allow_credentials",
+        env: "ACCESS_CONTROL_ALLOW_CREDENTIALS",
+        flag: Some("cors-allow-credentials"),
+        default: None,
+        secret: false,
+        help: "Value of `Access-Control-Allow-Credentials`",
+    },
+    Setting {
+        key: "cors.expose_headers",
+        env: "ACCESS_CONTROL_EXPOSE_HEADERS",
+        flag: Some("cors-expose-headers"),
+        default: Some(""),
+        secret: false,
+        help: "Value of `Access-Control-Expose-Headers`",
+    },
+    Setting {
+        key: "cors.max_age",
+        env: "ACCESS_CONTROL_MAX_AGE",
+        flag: Some("cors-max-age"),
+        default: Some("600"),
+        secret: false,
+        help: "Seconds browsers may cache a preflight response",
+    },
+    Setting {
+        key: "login.policy",
+        env: "LOGIN_POLICY",
+        flag: Some("login-policy"),
+        default: Some("unicode"),
+        secret: false,
+        help: "Characters allowed in logins: `ascii` or `unicode`",
+    },
+    Setting {
+        key: "i18n.default_locale",
+        env: "DEFAULT_LOCALE",
+        flag: Some("default-locale"),
+        default: Some("ru"),
+        secret: false,
+        help: "Locale used when the request does not ask for a supported one: `ru` or `en`",
+    },
+];
+
+#[derive(Debug, Clone)]
+pub enum ConfigError {
+    Cli(String),
+    File {
+        path: String,
+        message: String,
+    },
+    Missing {
+        key: &'static str,
+        env: &'static str,
+    },
+    Invalid {
+        key: &'static str,
+        env: &'static str,
+        message: String,
+    },
+}
+
+impl ConfigError {
+    fn missing(setting: &'static Setting) -> Self {
+        Self::Missing {
+            key: setting.key,
+            env: setting.env,
+        }
+    }
+
+    fn invalid(setting: &'static Setting, message: String) -> Self {
+        Self::Invalid {
+            key: setting.key,
+            env: setting.env,
+            message,
+        }
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Cli(message) => write!(f, "command line: {}", message.trim_end()),
+            Self::File { path, message } => {
+                write!(f, "config file `{}`: {}", path, message.trim_end())
+            }
+            Self::Missing { key, env } => {
+                write!(f, "`{}` must be set (ENV-variable `{}`)", key, env)
+            }
+            Self::Invalid { key, env, message } => {
+                write!(f, "`{}` (ENV-variable `{}`): {}", key, env, message)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBackend {
+    Memory,
+}
+
+impl FromStr for StorageBackend {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "memory" => Ok(Self::Memory),
+            _ => Err(()),
+        }
+    }
+}
+
+struct SameSiteSetting(SameSite);
+
+impl FromStr for SameSiteSetting {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "strict" => Ok(Self(SameSite::Strict)),
+            "lax" => Ok(Self(SameSite::Lax)),
+            "none" => Ok(Self(SameSite::None)),
+            _ => Err(()),
+        }
+    }
+}
+
+fn command() -> Command {
+    let command = Command::new(env!("CARGO_PKG_NAME"))
+        .version(env!("CARGO_PKG_VERSION"))
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("PATH")
+                .help("TOML configuration file"),
+        );
+
+    SETTINGS
+        .iter()
+        .fold(command, |command, setting| match setting.flag {
+            Some(flag) => command.arg(
+                Arg::new(setting.key)
+                    .long(flag)
+                    .value_name(setting.env)
+                    .help(setting.help),
+            ),
+            None => command,
+        })
+}
+
+fn flatten(prefix: &str, table: &Table, values: &mut HashMap<String, String>) {
+    for (name, value) in table {
+        let key = match prefix {
+            "" => name.clone(),
+            _ => format!("{}.{}", prefix, name),
+        };
+
+        match value {
+            Value::Table(table) => flatten(&key, table, values),
+            Value::String(value) => {
+                values.insert(key, value.clone());
+            }
+            Value::Array(items) if items.iter().all(Value::is_str) => {
+                let items: Vec<&str> = items.iter().filter_map(Value::as_str).collect();
+
+                values.insert(key, items.join("\n"));
+            }
+            value => {
+                values.insert(key, value.to_string());
+            }
+        }
+    }
+}
+
+fn setting(key: &str) -> &'static Setting {
+    SETTINGS
+        .iter()
+        .find(|setting| setting.key == key)
+        .expect("unknown setting")
+}
+
+// 64 characters, as `openssl rand -hex 32` prints: fewer hex digits never
+// reach the entropy estimate below.
+const MIN_SECRET_LENGTH: usize = 64;
+const MIN_SECRET_ENTROPY_BITS: f64 = 128.0;
+
+fn estimate_entropy_bits(secret: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+
+    for c in secret.chars() {
+        *counts.entry(c).or_default() += 1;
+    }
+
+    let length = secret.chars().count() as f64;
+
+    let per_char: f64 = counts
+        .values()
+        .map(|count| {
+            let p = *count as f64 / length;
+            -p * p.log2()
+        })
+        .sum();
+
+    per_char * length
+}
+
+fn check_secret(secret: &str) -> Result<(), String> {
+    if secret.chars().count() < MIN_SECRET_LENGTH {
+        return Err(format!(
+            "must be at least {} characters long, e.g. `openssl rand -hex 32`",
+            MIN_SECRET_LENGTH
+        ));
+    }
+
+    if estimate_entropy_bits(secret) < MIN_SECRET_ENTROPY_BITS {
+        return Err("is too predictable, generate it with e.g. `openssl rand -hex 32`".to_owned());
+    }
+
+    Ok(())
+}
+
+fn check_origin(origin: &str) -> Result<(), String> {
+    if origin == "*" {
+        return Ok(());
+    }
+
+    let uri: Uri = origin
+        .replacen("://*.", "://wildcard.", 1)
+        .parse()
+        .map_err(|_| format!("`{}` is not a valid URL", origin))?;
+
+    if !matches!(uri.scheme_str(), Some("http") | Some("https")) {
+        return Err(format!("`{}` must start with http:// or https://", origin));
+    }
+
+    if uri.host().is_none_or(str::is_empty) {
+        return Err(format!("`{}` has no host", origin));
+    }
+
+    if uri.path() != "/" && !uri.path().is_empty() || uri.query().is_some() || origin.ends_with('/')
+    {
+        return Err(format!(
+            "`{}` must be an origin without path, query or trailing slash",
+            origin
+        ));
+    }
+
+    Ok(())
+}
+
+fn check_header_value(value: &str) -> Result<(), String> {
+    HeaderValue::from_str(value)
+        .map(|_| ())
+        .map_err(|_| format!("`{}` is not a valid header value", value))
+}
+
+fn list(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split([',', '\n'])
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+struct Layers {
+    values: HashMap<String, String>,
+    errors: Vec<ConfigError>,
+}
+
+impl Layers {
+    fn collect<I, T>(args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let mut layers = Self {
+            values: HashMap::new(),
+            errors: vec![],
+        };
+
+        for setting in SETTINGS {
+            if let Some(default) = setting.default {
+                layers
+                    .values
+                    .insert(setting.key.to_owned(), default.to_owned());
+            }
+        }
+
+        let matches = match command().try_get_matches_from(args) {
+            Ok(matches) => Some(matches),
+            Err(error) => match error.kind() {
+                ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => error.exit(),
+                _ => {
+                    layers.errors.push(ConfigError::Cli(error.to_string()));
+                    None
+                }
+            },
+        };
+
+        let mut file = layers.read_file(matches.as_ref());
+
+        layers.resolve_secret_files(&mut file, |setting| format!("{}_file", setting.key));
+
+        layers.values.extend(file);
+
+        let mut env: HashMap<String, String> = SETTINGS
+            .iter()
+            .flat_map(|setting| {
+                let file_env = format!("{}_FILE", setting.env);
+
+                [
+                    var(setting.env)
+                        .ok()
+                        .map(|value| (setting.key.to_owned(), value)),
+                    var(&file_env)
+                        .ok()
+                        .filter(|_| setting.secret)
+                        .map(|path| (format!("{}_file", setting.key), path)),
+                ]
+            })
+            .flatten()
+            .collect();
+
+        layers.resolve_secret_files(&mut env, |setting| format!("{}_FILE", setting.env));
+
+        layers.values.extend(env);
+
+        if let Some(matches) = matches {
+            for setting in SETTINGS.iter().filter(|setting| setting.flag.is_some()) {
+                if let Some(value) = matches.get_one::<String>(setting.key) {
+                    layers
+                        .values
+                        .insert(setting.key.to_owned(), value.to_owned());
+                }
+            }
+        }
+
+        layers
+    }
+
+    fn read_file(&mut self, matches: Option<&ArgMatches>) -> HashMap<String, String> {
+        let mut values = HashMap::new();
+
+        let explicit = matches
+            .and_then(|matches| matches.get_one::<String>("config").cloned())
+            .or_else(|| var("CONFIG_FILE").ok());
+
+        let path = match explicit {
+            Some(path) => path,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => DEFAULT_CONFIG_FILE.to_owned(),
+            None => return values,
+        };
+
+        let table = read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|content| content.parse::<Table>().map_err(|error| error.to_string()));
+
+        match table {
+            Ok(table) => flatten("", &table, &mut values),
+            Err(message) => self.errors.push(ConfigError::File { path, message }),
+        }
+
+        values
+    }
+
+    fn resolve_secret_files(
+        &mut self,
+        values: &mut HashMap<String, String>,
+        source: impl Fn(&Setting) -> String,
+    ) {
+        for setting in SETTINGS.iter().filter(|setting| setting.secret) {
+            let path = match values.remove(&format!("{}_file", setting.key)) {
+                Some(path) => path,
+                None => continue,
+            };
+
+            if values.contains_key(setting.key) {
+                self.errors.push(ConfigError::invalid(
+                    setting,
+                    format!("set either the value or `{}`, not both", source(setting)),
+                ));
+                continue;
+            }
+
+            match read_to_string(&path) {
+                Ok(content) => {
+                    let content = content.trim_end_matches(['\n', '\r']).to_owned();
+                    values.insert(setting.key.to_owned(), content);
+                }
+                Err(error) => self.errors.push(ConfigError::invalid(
+                    setting,
+                    format!("can not read `{}`: {}", path, error),
+                )),
+            }
+        }
+    }
+
+    // Secrets may contain commas, so a list of them takes one `kid:secret`
+    // per line (or per TOML array item) instead.
+    fn key_list(&mut self, key: &str, value_name: &str) -> Vec<(String, String)> {
+        let setting = setting(key);
+
+        let value = self.values.get(key).cloned().unwrap_or_default();
+
+        let items: Vec<&str> = if setting.secret {
+            value
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .collect()
+        } else {
+            list(&value).collect()
+        };
+
+        let mut keys = vec![];
+
+        for item in items {
+            match item.split_once(':') {
+                Some((kid, value)) if !kid.trim().is_empty() && !value.is_empty() => {
+                    keys.push((kid.trim().to_owned(), value.to_owned()));
+                }
+                _ if setting.secret => self.errors.push(ConfigError::invalid(
+                    setting,
+                    format!("expected one `kid:{}` per line", value_name),
+                )),
+                _ => self.errors.push(ConfigError::invalid(
+                    setting,
+                    format!(
+                        "expected a list of `kid:{}` separated by commas",
+                        value_name
+                    ),
+                )),
+            }
+        }
+
+        keys
+    }
+
+    fn optional_string(&self, key: &str) -> String {
+        self.values.get(key).cloned().unwrap_or_default()
+    }
+
+    fn string(&mut self, key: &str) -> String {
+        let setting = setting(key);
+
+        match self.values.get(key) {
+            Some(value) => value.clone(),
+            None => {
+                self.errors.push(ConfigError::missing(setting));
+                String::new()
+            }
+        }
+    }
+
+    fn parsed<T: FromStr>(&mut self, key: &str, expected: &str) -> Option<T> {
+        let setting = setting(key);
+
+        let value = self.values.get(key)?;
+
+        match value.parse() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                self.errors.push(ConfigError::invalid(
+                    setting,
+                    format!("expected {}, got `{}`", expected, value),
+                ));
+                None
+            }
+        }
+    }
+
+    fn required<T: FromStr>(&mut self, key: &str, expected: &str, fallback: T) -> T {
+        if !self.values.contains_key(key) {
+            self.errors.push(ConfigError::missing(setting(key)));
+            return fallback;
+        }
+
+        self.parsed(key, expected).unwrap_or(fallback)
+    }
+}
+
+pub struct EnvConfig {
+    host: String,
+    port: u16,
+    workers: Option<usize>,
+    shutdown_delay: u64,
+    shutdown_timeout: u64,
+    log_level: String,
+    log_format: LogFormat,
+    tracing_exporter: TraceExporter,
+    tracing_otlp_endpoint: String,
+    tracing_service_name: String,
+    metrics_token: String,
+    tls_cert_file: String,
+    tls_key_file: String,
+    tls_redirect_port: Option<u16>,
+    storage_backend: StorageBackend,
+    storage_data_dir: String,
+    storage_snapshot_interval: u64,
+    storage_cache_ttl: u64,
+    storage_cache_capacity: usize,
+    jwt_algorithm: JwtAlgorithm,
+    jwt_secret: String,
+    jwt_private_key_file: String,
+    jwt_key_id: String,
+    jwt_previous_keys: Vec<(String, String)>,
+    jwt_previous_public_keys: Vec<(String, String)>,
+    jwt_ttl: u64,
+    jwt_domain: String,
+    jwt_same_site: SameSite,
+    access_control_allow_origin: String,
+    access_control_allow_methods: String,
+    access_control_allow_headers: String,
+    access_control_allow_credentials: String,
+    access_control_expose_headers: String,
+    access_control_max_age: u32,
+    login_policy: LoginPolicy,
+    default_locale: Locale,
+}
+
+impl EnvConfig {
+    pub fn load<I, T>(args: I) -> Result<Self, Vec<ConfigError>>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let mut layers = Layers::collect(args);
+
+        let jwt_algorithm = layers.required(
+            "jwt.algorithm",
+            "`hs256`, `rs256` or `eddsa`",
+            JwtAlgorithm::Hs256,
+        );
+
+        let (jwt_secret, jwt_private_key_file) = match jwt_algorithm {
+            JwtAlgorithm::Hs256 => (
+                layers.string("jwt.secret"),
+                layers.optional_string("jwt.private_key_file"),
+            ),
+            _ => (
+                layers.optional_string("jwt.secret"),
+                layers.string("jwt.private_key_file"),
+            ),
+        };
+
+        let config = Self {
+            host: layers.string("server.host"),
+            port: layers.required("server.port", "a port number", 0),
+            workers: layers.parsed("server.workers", "a positive number"),
+            shutdown_delay: layers.required("server.shutdown_delay", "a number of seconds", 0),
+            shutdown_timeout: layers.required("server.shutdown_timeout", "a number of seconds", 0),
+            log_level: layers.string("log.level"),
+            log_format: layers.required("log.format", "`json` or `text`", LogFormat::Json),
+            tracing_exporter: layers.required(
+                "tracing.exporter",
+                "`none`, `otlp` or `stdout`",
+                TraceExporter::None,
+            ),
+            tracing_otlp_endpoint: layers.string("tracing.otlp_endpoint"),
+            tracing_service_name: layers.string("tracing.service_name"),
+            metrics_token: layers.optional_string("metrics.token"),
+            tls_cert_file: layers.optional_string("tls.cert_file"),
+            tls_key_file: layers.optional_string("tls.key_file"),
+            tls_redirect_port: layers.parsed("tls.redirect_port", "a port number"),
+            storage_backend: layers.required("storage.backend", "`memory`", StorageBackend::Memory),
+            storage_data_dir: layers.optional_string("storage.data_dir"),
+            storage_snapshot_interval: layers.required(
+                "storage.snapshot_interval",
+                "a number of seconds",
+                0,
+            ),
+            storage_cache_ttl: layers.required("storage.cache_ttl", "a number of seconds", 0),
+            storage_cache_capacity: layers.required(
+                "storage.cache_capacity",
+                "a number of users",
+                0,
+            ),
+            jwt_algorithm,
+            jwt_secret,
+            jwt_private_key_file,
+            jwt_key_id: layers.string("jwt.key_id"),
+            jwt_previous_keys: layers.key_list("jwt.previous_keys", "secret"),
+            jwt_previous_public_keys: layers.key_list("jwt.previous_public_keys", "path"),
+            jwt_ttl: layers.required("jwt.ttl", "a number of seconds", 0),
+            jwt_domain: layers.string("jwt.domain"),
+            jwt_same_site: layers
+                .required::<SameSiteSetting>(
+                    "jwt.same_site",
+                    "`strict`, `lax` or `none`",
+                    SameSiteSetting(SameSite::Lax),
+                )
+                .0,
+            access_control_allow_origin: layers.string("cors.allow_origin"),
+            access_control_allow_methods: layers.string("cors.allow_methods"),
+            access_control_allow_headers: layers.string("cors.allow_headers"),
+            access_control_allow_credentials: layers.string("cors.allow_credentials"),
+            access_control_expose_headers: layers.string("cors.expose_headers"),
+            access_control_max_age: layers.required("cors.max_age", "a number of seconds", 0),
+            login_policy: layers.required(
+                "login.policy",
+                "`ascii` or `unicode`",
+                LoginPolicy::Unicode,
+            ),
+            default_locale: layers.required("i18n.default_locale", "`ru` or `en`", Locale::Ru),
+        };
+
+        if config.workers == Some(0) {
+            layers.errors.push(ConfigError::invalid(
+                setting("server.workers"),
+                "expected a positive number, got `0`".to_owned(),
+            ));
+        }
+
+        if layers.errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(layers.errors)
+        }
+    }
+
+    pub fn check(&self) -> Result<(), Vec<ConfigError>> {
+        let mut errors = vec![];
+
+        let mut invalid = |key: &str, message: String| {
+            errors.push(ConfigError::invalid(setting(key), message));
+        };
+
+        if self.host.trim().is_empty() {
+            invalid("server.host", "must not be empty".to_owned());
+        }
+
+        if self.port == 0 {
+            invalid("server.port", "must not be 0".to_owned());
+        }
+
+        if let Err(error) = EnvFilter::try_new(&self.log_level) {
+            invalid("log.level", error.to_string());
+        }
+
+        if self.tracing_exporter == TraceExporter::Otlp {
+            let is_http_url = self
+                .tracing_otlp_endpoint
+                .parse::<Uri>()
+                .map(|uri| {
+                    matches!(uri.scheme_str(), Some("http" | "https")) && uri.host().is_some()
+                })
+                .unwrap_or(false);
+
+            if !is_http_url {
+                invalid("tracing.otlp_endpoint", "must be an http(s) URL".to_owned());
+            }
+        }
+
+        if self.tracing_service_name.trim().is_empty() {
+            invalid("tracing.service_name", "must not be empty".to_owned());
+        }
+
+        if self.is_metrics_enabled() {
+            if let Err(message) = check_secret(&self.metrics_token) {
+                invalid("metrics.token", message);
+            }
+        }
+
+        if !self.storage_data_dir.is_empty() && Path::new(&self.storage_data_dir).is_file() {
+            invalid("storage.data_dir", "must be a directory".to_owned());
+        }
+
+        match (self.tls_cert_file.is_empty(), self.tls_key_file.is_empty()) {
+            (true, true) => {}
+            (false, true) => invalid(
+                "tls.key_file",
+                "must be set with `tls.cert_file`".to_owned(),
+            ),
+            (true, false) => invalid(
+                "tls.cert_file",
+                "must be set with `tls.key_file`".to_owned(),
+            ),
+            (false, false) => {
+                if let Err(message) = load_certified_key(&self.tls_cert_file, &self.tls_key_file) {
+                    invalid("tls.cert_file", message);
+                }
+            }
+        }
+
+        match self.tls_redirect_port {
+            Some(_) if !self.is_tls_enabled() => invalid(
+                "tls.redirect_port",
+                "requires `tls.cert_file` and `tls.key_file`".to_owned(),
+            ),
+            Some(port) if port == 0 || port == self.port => invalid(
+                "tls.redirect_port",
+                "must be a free port other than `server.port`".to_owned(),
+            ),
+            _ => {}
+        }
+
+        if self.jwt_algorithm == JwtAlgorithm::Hs256 {
+            if let Err(message) = check_secret(&self.jwt_secret) {
+                invalid("jwt.secret", message);
+            }
+        }
+
+        if self.jwt_key_id.trim().is_empty() {
+            invalid("jwt.key_id", "must not be empty".to_owned());
+        }
+
+        let mut key_ids = vec![self.jwt_key_id.as_str()];
+
+        for (kid, secret) in &self.jwt_previous_keys {
+            if key_ids.contains(&kid.as_str()) {
+                invalid(
+                    "jwt.previous_keys",
+                    format!("key id `{}` is used twice", kid),
+                );
+            }
+
+            if let Err(message) = check_secret(secret) {
+                invalid("jwt.previous_keys", format!("key `{}` {}", kid, message));
+            }
+
+            key_ids.push(kid);
+        }
+
+        for (kid, _) in &self.jwt_previous_public_keys {
+            if key_ids.contains(&kid.as_str()) {
+                invalid(
+                    "jwt.previous_public_keys",
+                    format!("key id `{}` is used twice", kid),
+                );
+            }
+
+            key_ids.push(kid);
+        }
+
+        if self.jwt_ttl == 0 {
+            invalid("jwt.ttl", "must be at least 1 second".to_owned());
+        }
+
+        if let Err(key_errors) = KeyRing::load(self) {
+            for (key, message) in key_errors {
+                invalid(key, message);
+            }
+        }
+
+        if self.jwt_domain.is_empty()
+            || self
+                .jwt_domain
+                .chars()
+                .any(|c| c.is_whitespace() || c.is_control() || c == ';' || c == ',')
+        {
+            invalid(
+                "jwt.domain",
+                format!("`{}` is not a valid cookie domain", self.jwt_domain),
+            );
+        }
+
+        let origins: Vec<&str> = list(&self.access_control_allow_origin).collect();
+
+        if origins.is_empty() {
+            invalid(
+                "cors.allow_origin",
+                "must list at least one origin".to_owned(),
+            );
+        } else if origins.len() > 1 && origins.contains(&"*") {
+            invalid(
+                "cors.allow_origin",
+                "`*` can not be combined with other origins".to_owned(),
+            );
+        }
+
+        for origin in origins {
+            if let Err(message) = check_header_value(origin).and_then(|_| check_origin(origin)) {
+                invalid("cors.allow_origin", message);
+            }
+        }
+
+        if let Err(message) = check_header_value(&self.access_control_allow_methods) {
+            invalid("cors.allow_methods", message);
+        } else if let Some(method) = list(&self.access_control_allow_methods)
+            .find(|method| Method::from_bytes(method.as_bytes()).is_err())
+        {
+            invalid(
+                "cors.allow_methods",
+                format!("`{}` is not a valid HTTP method", method),
+            );
+        }
+
+        for (key, value) in [
+            ("cors.allow_headers", &self.access_control_allow_headers),
+            ("cors.expose_headers", &self.access_control_expose_headers),
+        ] {
+            if let Err(message) = check_header_value(value) {
+                invalid(key, message);
+            } else if let Some(name) = list(value).find(|name| HeaderName::from_str(name).is_err())
+            {
+                invalid(key, format!("`{}` is not a valid header name", name));
+            }
+        }
+
+        match self.access_control_allow_credentials.as_str() {
+            "true" if list(&self.access_control_allow_origin).any(|origin| origin == "*") => {
+                invalid(
+                    "cors.allow_credentials",
+                    "browsers reject credentials with the `*` origin".to_owned(),
+                )
+            }
+            "true" | "false" => {}
+            value => invalid(
+                "cors.allow_credentials",
+                format!("expected `true` or `false`, got `{}`", value),
+            ),
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn clone_host(&self) -> String {
+        self.host.clone()
+    }
+
+    pub fn get_port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn get_workers(&self) -> Option<usize> {
+        self.workers
+    }
+
+    pub fn get_shutdown_delay(&self) -> Duration {
+        Duration::from_secs(self.shutdown_delay)
+    }
+
+    pub fn get_shutdown_timeout(&self) -> u64 {
+        self.shutdown_timeout
+    }
+
+    pub fn clone_log_level(&self) -> String {
+        self.log_level.clone()
+    }
+
+    pub fn get_log_format(&self) -> LogFormat {
+        self.log_format
+    }
+
+    pub fn get_tracing_exporter(&self) -> TraceExporter {
+        self.tracing_exporter
+    }
+
+    pub fn clone_tracing_otlp_endpoint(&self) -> String {
+        self.tracing_otlp_endpoint.clone()
+    }
+
+    pub fn clone_tracing_service_name(&self) -> String {
+        self.tracing_service_name.clone()
+    }
+
+    pub fn is_metrics_enabled(&self) -> bool {
+        !self.metrics_token.is_empty()
+    }
+
+    pub fn clone_metrics_token(&self) -> String {
+        self.metrics_token.clone()
+    }
+
+    pub fn is_tls_enabled(&self) -> bool {
+        !self.tls_cert_file.is_empty() && !self.tls_key_file.is_empty()
+    }
+
+    pub fn clone_tls_cert_file(&self) -> String {
+        self.tls_cert_file.clone()
+    }
+
+    pub fn clone_tls_key_file(&self) -> String {
+        self.tls_key_file.clone()
+    }
+
+    pub fn get_tls_redirect_port(&self) -> Option<u16> {
+        self.tls_redirect_port
+    }
+
+    pub fn get_storage_backend(&self) -> StorageBackend {
+        self.storage_backend
+    }
+
+    pub fn clone_storage_data_dir(&self) -> String {
+        self.storage_data_dir.clone()
+    }
+
+    pub fn get_storage_snapshot_interval(&self) -> Option<Duration> {
+        match self.storage_snapshot_interval {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds)),
+        }
+    }
+
+    pub fn get_storage_cache(&self) -> Option<(Duration, usize)> {
+        match (self.storage_cache_ttl, self.storage_cache_capacity) {
+            (0, _) | (_, 0) => None,
+            (seconds, capacity) => Some((Duration::from_secs(seconds), capacity)),
+        }
+    }
+
+    pub fn clone_jwt_secret(&self) -> String {
+        self.jwt_secret.clone()
+    }
+
+    pub fn get_jwt_algorithm(&self) -> JwtAlgorithm {
+        self.jwt_algorithm
+    }
+
+    pub fn clone_jwt_private_key_file(&self) -> String {
+        self.jwt_private_key_file.clone()
+    }
+
+    pub fn clone_jwt_key_id(&self) -> String {
+        self.jwt_key_id.clone()
+    }
+
+    pub fn clone_jwt_previous_keys(&self) -> Vec<(String, String)> {
+        self.jwt_previous_keys.clone()
+    }
+
+    pub fn clone_jwt_previous_public_keys(&self) -> Vec<(String, String)> {
+        self.jwt_previous_public_keys.clone()
+    }
+
+    pub fn get_jwt_ttl(&self) -> Duration {
+        Duration::from_secs(self.jwt_ttl)
+    }
+
+    pub fn clone_jwt_domain(&self) -> String {
+        self.jwt_domain.clone()
+    }
+
+    pub fn get_jwt_same_site(&self) -> SameSite {
+        self.jwt_same_site
+    }
+
+    pub fn clone_access_control_allow_origin(&self) -> String {
+        self.access_control_allow_origin.clone()
+    }
+
+    pub fn clone_access_control_allow_methods(&self) -> String {
+        self.access_control_allow_methods.clone()
+    }
+
+    pub fn clone_access_control_allow_headers(&self) -> String {
+        self.access_control_allow_headers.clone()
+    }
+
+    pub fn clone_access_control_allow_credentials(&self) -> String {
+        self.access_control_allow_credentials.clone()
+    }
+
+    pub fn clone_access_control_expose_headers(&self) -> String {
+        self.access_control_expose_headers.clone()
+    }
+
+    pub fn get_access_control_max_age(&self) -> u32 {
+        self.access_control_max_age
+    }
+
+    pub fn get_login_policy(&self) -> LoginPolicy {
+        self.login_policy
+    }
+
+    pub fn get_default_locale(&self) -> Locale {
+        self.default_locale
+    }
+}
+
+pub fn exit_with_config_errors(errors: Vec<ConfigError>) -> ! {
+    eprintln!("Invalid configuration:");
+
+    for error in errors {
+        eprintln!("  - {}", error);
+    }
+
+    exit(1);
+}
+
+#[derive(Default)]
+pub struct GlobalConfig(OnceLock<EnvConfig>);
+
+impl GlobalConfig {
+    pub const fn new() -> Self {
+        Self(OnceLock::new())
+    }
+
+    pub fn init(&self, config: EnvConfig) {
+        if self.0.set(config).is_err() {
+            panic!("configuration is already initialized");
+        }
+    }
+}
+
+impl std::ops::Deref for GlobalConfig {
+    type Target = EnvConfig;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.get().expect("configuration is not initialized")
+    }
+}
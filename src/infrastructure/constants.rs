@@ -1,7 +1,13 @@
 use lazy_static::lazy_static;
 
-use super::models::EnvConfig;
+use super::config::GlobalConfig;
+use super::signing::KeyRing;
+
+pub static ENV_CONFIG: GlobalConfig = GlobalConfig::new();
 
 lazy_static! {
-    pub static ref ENV_CONFIG: EnvConfig = EnvConfig::new();
+    // `EnvConfig::check` has already loaded the same keys at startup, so this
+    // can not fail afterwards.
+    pub static ref KEY_RING: KeyRing =
+        KeyRing::load(&ENV_CONFIG).unwrap_or_else(|_| panic!("JWT keys can not be loaded"));
 }
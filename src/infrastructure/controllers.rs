@@ -1,7 +1,37 @@
-use actix_web::web::{scope, ServiceConfig};
+use actix_web::{
+    http::header::{CacheControl, CacheDirective},
+    web::{get, scope, ServiceConfig},
+    HttpResponse,
+};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
+use super::constants::{ENV_CONFIG, KEY_RING};
+use super::health::controllers::configure as configure_health;
+use super::metrics::get_metrics;
+use super::openapi::ApiDoc;
 use super::user::controllers::configure as configure_user;
 
+pub async fn get_jwks() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(CacheControl(vec![
+            CacheDirective::Public,
+            CacheDirective::MaxAge(300),
+        ]))
+        .json(KEY_RING.to_jwks())
+}
+
 pub fn configure(cfg: &mut ServiceConfig) {
-    cfg.service(scope("/api/v1").configure(configure_user));
+    if ENV_CONFIG.is_metrics_enabled() {
+        cfg.route("/metrics", get().to(get_metrics));
+    }
+
+    cfg.configure(configure_health)
+        .route("/.well-known/jwks.json", get().to(get_jwks))
+        // Registered ahead of the `/api/v1` scope, which would otherwise
+        // answer these paths with 404.
+        .service(
+            SwaggerUi::new("/api/v1/docs/{_:.*}").url("/api/v1/openapi.json", ApiDoc::openapi()),
+        )
+        .service(scope("/api/v1").configure(configure_user));
 }
@@ -0,0 +1,250 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{self, HeaderMap, HeaderValue};
+use actix_web::http::Method;
+use actix_web::{Error as WebActixError, HttpResponse};
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+
+use super::constants::ENV_CONFIG;
+
+enum AllowedOrigin {
+    Any,
+    Exact(String),
+    // `https://*.example.com`: every subdomain of `example.com` over https,
+    // but not `example.com` itself.
+    Subdomains { scheme: String, suffix: String },
+}
+
+impl AllowedOrigin {
+    fn parse(value: &str) -> Self {
+        if value == "*" {
+            return Self::Any;
+        }
+
+        match value.split_once("://*.") {
+            Some((scheme, suffix)) => Self::Subdomains {
+                scheme: scheme.to_ascii_lowercase(),
+                suffix: format!(".{}", suffix.to_ascii_lowercase()),
+            },
+            None => Self::Exact(value.to_ascii_lowercase()),
+        }
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Exact(allowed) => *allowed == origin,
+            Self::Subdomains { scheme, suffix } => origin
+                .strip_prefix(scheme.as_str())
+                .and_then(|rest| rest.strip_prefix("://"))
+                .and_then(|host| host.strip_suffix(suffix.as_str()))
+                .is_some_and(|subdomain| {
+                    !subdomain.is_empty()
+                        && subdomain
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+                }),
+        }
+    }
+}
+
+pub struct OriginAllowlist(Vec<AllowedOrigin>);
+
+impl OriginAllowlist {
+    pub fn from_config() -> Self {
+        Self(
+            ENV_CONFIG
+                .clone_access_control_allow_origin()
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(AllowedOrigin::parse)
+                .collect(),
+        )
+    }
+
+    fn find(&self, origin: &str) -> Option<&AllowedOrigin> {
+        let origin = origin.to_ascii_lowercase();
+
+        self.0.iter().find(|allowed| allowed.matches(&origin))
+    }
+
+    // Whether the origin is named by the allowlist. Unlike CORS, `*` does
+    // not count: it means "anyone may read", not "anyone may act".
+    pub fn is_trusted(&self, origin: &str) -> bool {
+        self.find(origin)
+            .is_some_and(|allowed| !matches!(allowed, AllowedOrigin::Any))
+    }
+}
+
+struct CorsPolicy {
+    origins: OriginAllowlist,
+    allow_methods: HeaderValue,
+    allow_headers: HeaderValue,
+    expose_headers: Option<HeaderValue>,
+    allow_credentials: bool,
+    max_age: HeaderValue,
+}
+
+impl CorsPolicy {
+    fn from_config() -> Self {
+        let expose_headers = ENV_CONFIG.clone_access_control_expose_headers();
+
+        Self {
+            origins: OriginAllowlist::from_config(),
+            allow_methods: HeaderValue::from_str(&ENV_CONFIG.clone_access_control_allow_methods())
+                .unwrap(),
+            allow_headers: HeaderValue::from_str(&ENV_CONFIG.clone_access_control_allow_headers())
+                .unwrap(),
+            expose_headers: (!expose_headers.trim().is_empty())
+                .then(|| HeaderValue::from_str(&expose_headers).unwrap()),
+            allow_credentials: ENV_CONFIG.clone_access_control_allow_credentials() == "true",
+            max_age: HeaderValue::from(ENV_CONFIG.get_access_control_max_age()),
+        }
+    }
+
+    fn allow_origin(&self, origin: &HeaderValue) -> Option<HeaderValue> {
+        let allowed = self.origins.find(origin.to_str().ok()?)?;
+
+        match allowed {
+            AllowedOrigin::Any if !self.allow_credentials => Some(HeaderValue::from_static("*")),
+            _ => Some(origin.clone()),
+        }
+    }
+
+    fn insert_headers(&self, headers: &mut HeaderMap, allow_origin: HeaderValue) {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+
+        if self.allow_credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+    }
+
+    fn preflight_response(&self, allow_origin: Option<HeaderValue>) -> HttpResponse {
+        let allow_origin = match allow_origin {
+            Some(allow_origin) => allow_origin,
+            None => return HttpResponse::Forbidden().finish(),
+        };
+
+        let mut response = HttpResponse::NoContent().finish();
+
+        let headers = response.headers_mut();
+
+        self.insert_headers(headers, allow_origin);
+
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            self.allow_methods.clone(),
+        );
+
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_HEADERS,
+            self.allow_headers.clone(),
+        );
+
+        headers.insert(header::ACCESS_CONTROL_MAX_AGE, self.max_age.clone());
+
+        response
+    }
+}
+
+#[derive(Default)]
+pub struct Cors {}
+
+pub struct CorsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+{
+    service: Rc<S>,
+    policy: Rc<CorsPolicy>,
+}
+
+impl<S> Transform<S, ServiceRequest> for Cors
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse;
+    type Error = WebActixError;
+    type Transform = CorsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CorsMiddleware {
+            service: Rc::new(service),
+            policy: Rc::new(CorsPolicy::from_config()),
+        }))
+    }
+}
+
+impl<S> Service<ServiceRequest> for CorsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse;
+    type Error = WebActixError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let policy = self.policy.clone();
+
+        let headers = req.headers();
+
+        let allow_origin = headers
+            .get(header::ORIGIN)
+            .and_then(|origin| policy.allow_origin(origin));
+
+        let is_preflight = req.method() == Method::OPTIONS
+            && headers.contains_key(header::ORIGIN)
+            && headers.contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+
+        if is_preflight {
+            let mut response = policy.preflight_response(allow_origin);
+
+            let headers = response.headers_mut();
+
+            headers.append(header::VARY, HeaderValue::from_static("Origin"));
+            headers.append(
+                header::VARY,
+                HeaderValue::from_static("Access-Control-Request-Method"),
+            );
+            headers.append(
+                header::VARY,
+                HeaderValue::from_static("Access-Control-Request-Headers"),
+            );
+
+            return Box::pin(ready(Ok(req.into_response(response))));
+        }
+
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let mut service_response = fut.await?;
+
+            let headers = service_response.response_mut().headers_mut();
+
+            headers.append(header::VARY, HeaderValue::from_static("Origin"));
+
+            if let Some(allow_origin) = allow_origin {
+                policy.insert_headers(headers, allow_origin);
+
+                if let Some(expose_headers) = &policy.expose_headers {
+                    headers.insert(
+                        header::ACCESS_CONTROL_EXPOSE_HEADERS,
+                        expose_headers.clone(),
+                    );
+                }
+            }
+
+            Ok(service_response)
+        })
+    }
+}
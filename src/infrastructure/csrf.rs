@@ -0,0 +1,110 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, Uri};
+use actix_web::Error as WebActixError;
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+
+use super::{cors::OriginAllowlist, errors::ApiError};
+
+// `GET`, `HEAD` and `OPTIONS` pass through. Other methods need an `Origin`
+// (or, failing that, `Referer`) that is either the server itself or listed
+// in `cors.allow_origin`. Requests naming neither are only accepted when they
+// do not carry the session cookie, which keeps non-browser clients working.
+// Wrap every scope whose mutating routes rely on that cookie.
+pub struct Csrf {
+    session_cookie: &'static str,
+}
+
+impl Csrf {
+    pub fn new(session_cookie: &'static str) -> Self {
+        Self { session_cookie }
+    }
+}
+
+pub struct CsrfMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+{
+    service: Rc<S>,
+    session_cookie: &'static str,
+    trusted_origins: Rc<OriginAllowlist>,
+}
+
+impl<S> Transform<S, ServiceRequest> for Csrf
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse;
+    type Error = WebActixError;
+    type Transform = CsrfMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CsrfMiddleware {
+            service: Rc::new(service),
+            session_cookie: self.session_cookie,
+            trusted_origins: Rc::new(OriginAllowlist::from_config()),
+        }))
+    }
+}
+
+fn referer_origin(referer: &str) -> Option<String> {
+    let uri: Uri = referer.parse().ok()?;
+
+    Some(format!("{}://{}", uri.scheme_str()?, uri.authority()?))
+}
+
+impl<S> CsrfMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+{
+    fn is_allowed(&self, req: &ServiceRequest) -> bool {
+        if req.method().is_safe() {
+            return true;
+        }
+
+        let headers = req.headers();
+
+        let origin = match headers.get(header::ORIGIN) {
+            Some(origin) => origin.to_str().ok().map(str::to_owned),
+            None => headers
+                .get(header::REFERER)
+                .and_then(|referer| referer.to_str().ok())
+                .and_then(referer_origin),
+        };
+
+        let origin = match origin {
+            Some(origin) => origin,
+            None => return req.cookie(self.session_cookie).is_none(),
+        };
+
+        let connection_info = req.connection_info();
+
+        let own_origin = format!("{}://{}", connection_info.scheme(), connection_info.host());
+
+        origin.eq_ignore_ascii_case(&own_origin) || self.trusted_origins.is_trusted(&origin)
+    }
+}
+
+impl<S> Service<ServiceRequest> for CsrfMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse;
+    type Error = WebActixError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if !self.is_allowed(&req) {
+            return Box::pin(ready(Ok(req.error_response(ApiError::CrossSiteRequest))));
+        }
+
+        Box::pin(self.service.call(req))
+    }
+}
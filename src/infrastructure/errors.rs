@@ -0,0 +1,195 @@
+use actix_web::{
+    http::{header::ContentType, StatusCode},
+    HttpResponse, ResponseError,
+};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use tracing::error;
+use validator::ValidationErrors;
+
+use crate::core::error::ErrorSource;
+use crate::core::user::login::LoginPolicyError;
+use crate::core::user::service::{
+    UserServiceGetManyError, UserServiceGetOneError, UserServiceImportError, UserServiceLoginError,
+    UserServiceRegisterError, UserServiceUpdateError,
+};
+
+use super::{
+    constants::ENV_CONFIG,
+    i18n::{translate, Locale},
+    models::{ErrorDTO, ValidationErrorDTO},
+    validation::{field_errors, login_errors},
+};
+
+#[derive(Debug, Clone)]
+pub enum ApiError {
+    MalformedRequest(String),
+    Validation(ValidationErrors),
+    Unauthorized,
+    CrossSiteRequest,
+    Forbidden,
+    UserNotFound,
+    LoginAlreadyUsed,
+    LoginRepeated,
+    LoginNotAllowed,
+    LoginMixedScript,
+    WrongCredentials,
+    AccountBanned,
+    UnexpectedError,
+}
+
+impl ApiError {
+    // Part of the public API: never change an existing code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::MalformedRequest(_) => "malformed_request",
+            Self::Validation(_) => "validation_failed",
+            Self::Unauthorized => "unauthorized",
+            Self::CrossSiteRequest => "cross_site_request",
+            Self::Forbidden => "forbidden",
+            Self::UserNotFound => "user_not_found",
+            Self::LoginAlreadyUsed => "login_already_used",
+            Self::LoginRepeated => "login_repeated",
+            Self::LoginNotAllowed => "login_not_allowed",
+            Self::LoginMixedScript => "login_mixed_script",
+            Self::WrongCredentials => "wrong_credentials",
+            Self::AccountBanned => "account_banned",
+            Self::UnexpectedError => "unexpected_error",
+        }
+    }
+
+    pub fn message(&self, locale: Locale) -> String {
+        let message = translate(locale, self.code()).unwrap_or(self.code());
+
+        match self {
+            Self::MalformedRequest(details) => format!("{}: {}", message, details),
+            _ => message.to_owned(),
+        }
+    }
+
+    pub fn to_body(&self, locale: Locale) -> String {
+        let body = match self {
+            Self::Validation(errors) => serde_json::to_string(&ValidationErrorDTO::new(
+                self.code(),
+                &self.message(locale),
+                field_errors(errors, locale),
+            )),
+            _ => serde_json::to_string(&ErrorDTO::new(self.code(), &self.message(locale))),
+        };
+
+        body.unwrap_or_default()
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{}: {}",
+            self.code(),
+            self.message(ENV_CONFIG.get_default_locale())
+        )
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::MalformedRequest(_)
+            | Self::Validation(_)
+            | Self::LoginAlreadyUsed
+            | Self::LoginRepeated
+            | Self::LoginNotAllowed
+            | Self::LoginMixedScript
+            | Self::WrongCredentials => StatusCode::BAD_REQUEST,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::CrossSiteRequest | Self::Forbidden | Self::AccountBanned => StatusCode::FORBIDDEN,
+            Self::UserNotFound => StatusCode::NOT_FOUND,
+            Self::UnexpectedError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code())
+            .content_type(ContentType::json())
+            .body(self.to_body(ENV_CONFIG.get_default_locale()))
+    }
+}
+
+pub fn unexpected(source: ErrorSource) -> ApiError {
+    error!(error = %source, "unexpected error");
+
+    ApiError::UnexpectedError
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(errors: ValidationErrors) -> Self {
+        Self::Validation(errors)
+    }
+}
+
+impl From<UserServiceGetManyError> for ApiError {
+    fn from(error: UserServiceGetManyError) -> Self {
+        match error {
+            UserServiceGetManyError::UnexpectedError(source) => unexpected(source),
+        }
+    }
+}
+
+impl From<UserServiceGetOneError> for ApiError {
+    fn from(error: UserServiceGetOneError) -> Self {
+        match error {
+            UserServiceGetOneError::NotFound => Self::UserNotFound,
+            UserServiceGetOneError::UnexpectedError(source) => unexpected(source),
+        }
+    }
+}
+
+impl From<UserServiceRegisterError> for ApiError {
+    fn from(error: UserServiceRegisterError) -> Self {
+        match error {
+            UserServiceRegisterError::LoginAlreadyUsed => Self::LoginAlreadyUsed,
+            UserServiceRegisterError::LoginLength => {
+                Self::Validation(login_errors(LoginPolicyError::Length))
+            }
+            UserServiceRegisterError::LoginNotAllowed => Self::LoginNotAllowed,
+            UserServiceRegisterError::LoginMixedScript => Self::LoginMixedScript,
+            UserServiceRegisterError::UnexpectedError(source) => unexpected(source),
+        }
+    }
+}
+
+impl From<UserServiceLoginError> for ApiError {
+    fn from(error: UserServiceLoginError) -> Self {
+        match error {
+            UserServiceLoginError::NotFound | UserServiceLoginError::WrongPassword => {
+                Self::WrongCredentials
+            }
+            UserServiceLoginError::Banned => Self::AccountBanned,
+            UserServiceLoginError::UnexpectedError(source) => unexpected(source),
+        }
+    }
+}
+
+impl From<UserServiceUpdateError> for ApiError {
+    fn from(error: UserServiceUpdateError) -> Self {
+        match error {
+            UserServiceUpdateError::NotFound => Self::UserNotFound,
+            UserServiceUpdateError::UnexpectedError(source) => unexpected(source),
+        }
+    }
+}
+
+impl From<UserServiceImportError> for ApiError {
+    fn from(error: UserServiceImportError) -> Self {
+        match error {
+            UserServiceImportError::LoginAlreadyUsed => Self::LoginAlreadyUsed,
+            UserServiceImportError::LoginRepeated => Self::LoginRepeated,
+            UserServiceImportError::LoginLength => {
+                Self::Validation(login_errors(LoginPolicyError::Length))
+            }
+            UserServiceImportError::LoginNotAllowed => Self::LoginNotAllowed,
+            UserServiceImportError::LoginMixedScript => Self::LoginMixedScript,
+            UserServiceImportError::UnexpectedError(source) => unexpected(source),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use actix_web::{
+    web::{get, Data, ServiceConfig},
+    HttpResponse, Responder,
+};
+use std::collections::BTreeMap;
+use tracing::warn;
+
+use crate::core::user::repository::{UserRepository, UserRepositoryHealthCheckError};
+use crate::infrastructure::{shutdown::is_shutting_down, tls::is_cert_reload_failed};
+
+use super::models::{ComponentStatusDTO, HealthResDTO, ReadinessResDTO, VersionResDTO};
+
+pub async fn get_health() -> impl Responder {
+    HttpResponse::Ok().json(HealthResDTO::default())
+}
+
+pub async fn get_readiness(user_repository: Data<dyn UserRepository>) -> impl Responder {
+    let mut components = BTreeMap::new();
+
+    let storage = match user_repository.health_check().await {
+        Ok(()) => true,
+        Err(UserRepositoryHealthCheckError::Unavailable(source)) => {
+            warn!(component = "storage", error = %source, "not ready");
+            false
+        }
+    };
+
+    components.insert("storage".to_owned(), ComponentStatusDTO::new(storage));
+
+    // The server does not start with an invalid configuration; only the TLS
+    // certificate files can break afterwards, and their watcher logs why.
+    components.insert(
+        "config".to_owned(),
+        ComponentStatusDTO::new(!is_cert_reload_failed()),
+    );
+
+    components.insert(
+        "server".to_owned(),
+        ComponentStatusDTO::new(!is_shutting_down()),
+    );
+
+    let dto = ReadinessResDTO::new(components);
+
+    if dto.is_up() {
+        HttpResponse::Ok().json(dto)
+    } else {
+        HttpResponse::ServiceUnavailable().json(dto)
+    }
+}
+
+pub async fn get_version() -> impl Responder {
+    HttpResponse::Ok().json(VersionResDTO::default())
+}
+
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.route("/healthz", get().to(get_health))
+        .route("/readyz", get().to(get_readiness))
+        .route("/version", get().to(get_version));
+}
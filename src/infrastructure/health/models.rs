@@ -0,0 +1,77 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+const UP: &str = "up";
+const DOWN: &str = "down";
+
+#[derive(Serialize)]
+pub struct HealthResDTO {
+    status: String,
+}
+
+impl Default for HealthResDTO {
+    fn default() -> Self {
+        Self {
+            status: UP.to_owned(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ComponentStatusDTO {
+    status: String,
+}
+
+impl ComponentStatusDTO {
+    pub fn new(is_up: bool) -> Self {
+        Self {
+            status: if is_up { UP } else { DOWN }.to_owned(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ReadinessResDTO {
+    status: String,
+    components: BTreeMap<String, ComponentStatusDTO>,
+}
+
+impl ReadinessResDTO {
+    pub fn new(components: BTreeMap<String, ComponentStatusDTO>) -> Self {
+        let status = if components.values().all(|component| component.status == UP) {
+            UP
+        } else {
+            DOWN
+        };
+
+        Self {
+            status: status.to_owned(),
+            components,
+        }
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.status == UP
+    }
+}
+
+#[derive(Serialize)]
+pub struct VersionResDTO {
+    version: String,
+    git_hash: String,
+    features: Vec<String>,
+}
+
+impl Default for VersionResDTO {
+    fn default() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            git_hash: env!("GIT_HASH").to_owned(),
+            features: env!("BUILD_FEATURES")
+                .split(',')
+                .filter(|feature| !feature.is_empty())
+                .map(str::to_owned)
+                .collect(),
+        }
+    }
+}
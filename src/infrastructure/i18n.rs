@@ -0,0 +1,314 @@
+use actix_web::body::BoxBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::{Error as WebActixError, HttpRequest};
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use super::{constants::ENV_CONFIG, errors::ApiError};
+
+pub const LOCALE_COOKIE: &str = "lang";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    Ru,
+    En,
+}
+
+impl Locale {
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Self::Ru => "ru",
+            Self::En => "en",
+        }
+    }
+
+    pub fn negotiate(req: &HttpRequest) -> Self {
+        let default = ENV_CONFIG.get_default_locale();
+
+        if let Some(locale) = req
+            .cookie(LOCALE_COOKIE)
+            .and_then(|cookie| cookie.value().parse().ok())
+        {
+            return locale;
+        }
+
+        req.headers()
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| Self::from_accept_language(value, default))
+            .unwrap_or(default)
+    }
+
+    fn from_accept_language(value: &str, default: Self) -> Self {
+        let mut ranges: Vec<(&str, f32)> = value
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map(|q| q.trim().parse::<f32>().unwrap_or(0.0))
+                    .unwrap_or(1.0);
+
+                if tag.is_empty() || quality <= 0.0 {
+                    return None;
+                }
+
+                Some((tag, quality))
+            })
+            .collect();
+
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        for (tag, _) in ranges {
+            if tag == "*" {
+                return default;
+            }
+
+            if let Ok(locale) = tag.parse() {
+                return locale;
+            }
+        }
+
+        default
+    }
+}
+
+impl FromStr for Locale {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let primary = s.split(['-', '_']).next().unwrap_or_default();
+
+        match primary.to_ascii_lowercase().as_str() {
+            "ru" => Ok(Self::Ru),
+            "en" => Ok(Self::En),
+            _ => Err(()),
+        }
+    }
+}
+
+// (key, ru, en)
+const MESSAGES: &[(&str, &str, &str)] = &[
+    (
+        "malformed_request",
+        "Некорректный запрос",
+        "Malformed request",
+    ),
+    (
+        "validation_failed",
+        "Некорректные данные запроса",
+        "Invalid request data",
+    ),
+    (
+        "unauthorized",
+        "Вы не авторизованы",
+        "You are not authorized",
+    ),
+    (
+        "cross_site_request",
+        "Запрос с другого сайта отклонён",
+        "Cross-site request rejected",
+    ),
+    (
+        "forbidden",
+        "Недостаточно прав для этого действия",
+        "You are not allowed to do this",
+    ),
+    ("user_not_found", "Пользователь не найден", "User not found"),
+    (
+        "login_already_used",
+        "Данный логин уже используется",
+        "This login is already in use",
+    ),
+    (
+        "login_repeated",
+        "Логин уже встречался выше в импортируемых данных",
+        "This login already appeared earlier in the imported data",
+    ),
+    (
+        "login_not_allowed",
+        "Логин содержит недопустимые символы",
+        "Login contains characters that are not allowed",
+    ),
+    (
+        "login_mixed_script",
+        "Логин содержит символы разных алфавитов",
+        "Login mixes characters from different scripts",
+    ),
+    (
+        "wrong_credentials",
+        "Неверный логин или пароль",
+        "Wrong login or password",
+    ),
+    (
+        "account_banned",
+        "Учётная запись заблокирована",
+        "This account is banned",
+    ),
+    ("unexpected_error", "Внезапная ошибка", "Unexpected error"),
+    (
+        "validation.required",
+        "Обязательное поле",
+        "This field is required",
+    ),
+    (
+        "validation.length",
+        "Длина: {min}-{max} символов",
+        "Length: {min}-{max} characters",
+    ),
+    (
+        "validation.range",
+        "Значение: {min}-{max}",
+        "Value: {min}-{max}",
+    ),
+    ("validation.regex", "Неверный формат", "Invalid format"),
+    (
+        "validation.invalid",
+        "Некорректное значение",
+        "Invalid value",
+    ),
+    (
+        "validation.login_required",
+        "Укажите логин",
+        "Login is required",
+    ),
+    (
+        "validation.login_length",
+        "Длина логина: 3-30 символов",
+        "Login length: 3-30 characters",
+    ),
+    (
+        "validation.login_whitespace",
+        "Логин не должен содержать пробелы",
+        "Login must not contain whitespace",
+    ),
+    (
+        "validation.password_required",
+        "Укажите пароль",
+        "Password is required",
+    ),
+    (
+        "validation.password_length",
+        "Длина пароля: 3-30 символов",
+        "Password length: 3-30 characters",
+    ),
+    (
+        "validation.password_hash_format",
+        "Хэш не соответствует алгоритму",
+        "Hash does not match the algorithm",
+    ),
+    (
+        "validation.role_format",
+        "Роли: строчные латинские буквы, цифры, `-` и `_`, до 32 символов",
+        "Roles: lowercase letters, digits, `-` and `_`, up to 32 characters",
+    ),
+    (
+        "validation.page_range",
+        "Номер страницы: 1-1000000",
+        "Page number: 1-1000000",
+    ),
+    (
+        "validation.per_page_range",
+        "Размер страницы: 1-100",
+        "Page size: 1-100",
+    ),
+    (
+        "validation.search_length",
+        "Длина строки поиска: до 30 символов",
+        "Search string length: up to 30 characters",
+    ),
+];
+
+fn lookup(locale: Locale, key: &str) -> Option<&'static str> {
+    MESSAGES
+        .iter()
+        .find(|(message_key, _, _)| *message_key == key)
+        .map(|(_, ru, en)| match locale {
+            Locale::Ru => *ru,
+            Locale::En => *en,
+        })
+}
+
+pub fn translate(locale: Locale, key: &str) -> Option<&'static str> {
+    lookup(locale, key).or_else(|| lookup(ENV_CONFIG.get_default_locale(), key))
+}
+
+#[derive(Default)]
+pub struct Localization {}
+
+pub struct LocalizationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+{
+    service: Rc<S>,
+}
+
+impl<S> Transform<S, ServiceRequest> for Localization
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse;
+    type Error = WebActixError;
+    type Transform = LocalizationMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(LocalizationMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+impl<S> Service<ServiceRequest> for LocalizationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse;
+    type Error = WebActixError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let locale = Locale::negotiate(req.request());
+
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let service_response = fut.await?;
+
+            let api_error = service_response
+                .response()
+                .error()
+                .and_then(|error| error.as_error::<ApiError>())
+                .cloned();
+
+            let api_error = match api_error {
+                Some(api_error) => api_error,
+                None => return Ok(service_response),
+            };
+
+            let (http_request, http_response) = service_response.into_parts();
+
+            let mut localized = http_response.set_body(BoxBody::new(api_error.to_body(locale)));
+
+            let headers = localized.headers_mut();
+
+            headers.insert(
+                header::CONTENT_LANGUAGE,
+                HeaderValue::from_static(locale.tag()),
+            );
+
+            headers.append(header::VARY, HeaderValue::from_static("Accept-Language"));
+
+            Ok(ServiceResponse::new(http_request, localized))
+        })
+    }
+}
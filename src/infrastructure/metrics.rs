@@ -0,0 +1,232 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{self, ContentType};
+use actix_web::http::Method;
+use actix_web::{Error as WebActixError, HttpRequest, HttpResponse};
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, Encoder, HistogramVec, IntCounterVec,
+    TextEncoder,
+};
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::time::Instant;
+
+use crate::core::error::ErrorSource;
+use crate::core::user::service::{UserServiceLoginError, UserServiceRegisterError};
+use crate::infrastructure::{
+    constants::ENV_CONFIG,
+    errors::{unexpected, ApiError},
+};
+
+lazy_static! {
+    static ref HTTP_REQUESTS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "http_requests_total",
+        "HTTP requests handled, by route pattern and status",
+        &["method", "route", "status"]
+    )
+    .unwrap();
+    static ref HTTP_REQUEST_DURATION_SECONDS: HistogramVec = register_histogram_vec!(
+        "http_request_duration_seconds",
+        "HTTP request latency, by route pattern and status",
+        &["method", "route", "status"]
+    )
+    .unwrap();
+    static ref USER_REGISTRATIONS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "user_registrations_total",
+        "Registration attempts, by result",
+        &["result"]
+    )
+    .unwrap();
+    static ref USER_LOGINS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "user_logins_total",
+        "Login attempts, by result",
+        &["result"]
+    )
+    .unwrap();
+    static ref AUTH_GUARD_REJECTIONS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "auth_guard_rejections_total",
+        "Requests rejected by the auth guard, by reason",
+        &["reason"]
+    )
+    .unwrap();
+    static ref USER_CACHE_LOOKUPS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "user_cache_lookups_total",
+        "User cache lookups, by key and result",
+        &["key", "result"]
+    )
+    .unwrap();
+    static ref USER_CACHE_EVICTIONS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "user_cache_evictions_total",
+        "Users dropped from the cache, by reason",
+        &["reason"]
+    )
+    .unwrap();
+}
+
+pub fn observe_registration<T>(result: &Result<T, UserServiceRegisterError>) {
+    let label = match result {
+        Ok(_) => "success",
+        Err(UserServiceRegisterError::UnexpectedError(_)) => "error",
+        Err(_) => "rejected",
+    };
+
+    USER_REGISTRATIONS_TOTAL.with_label_values(&[label]).inc();
+}
+
+pub fn observe_login<T>(result: &Result<T, UserServiceLoginError>) {
+    let label = match result {
+        Ok(_) => "success",
+        Err(UserServiceLoginError::UnexpectedError(_)) => "error",
+        Err(UserServiceLoginError::Banned) => "banned",
+        Err(_) => "wrong_credentials",
+    };
+
+    USER_LOGINS_TOTAL.with_label_values(&[label]).inc();
+}
+
+// `reason` is one of `missing_token`, `invalid_token`, `unknown_user`,
+// `banned`, `missing_role` or `error`.
+pub fn observe_auth_rejection(reason: &str) {
+    AUTH_GUARD_REJECTIONS_TOTAL
+        .with_label_values(&[reason])
+        .inc();
+}
+
+// `key` is `id` or `login`.
+pub fn observe_user_cache_lookup(key: &str, hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+
+    USER_CACHE_LOOKUPS_TOTAL
+        .with_label_values(&[key, result])
+        .inc();
+}
+
+// `reason` is one of `expired`, `capacity` or `updated`.
+pub fn observe_user_cache_eviction(reason: &str) {
+    USER_CACHE_EVICTIONS_TOTAL
+        .with_label_values(&[reason])
+        .inc();
+}
+
+// Compares every byte so the time taken does not tell how much matched.
+fn is_metrics_token(token: &str) -> bool {
+    let expected = ENV_CONFIG.clone_metrics_token();
+
+    token.len() == expected.len()
+        && token
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+pub async fn get_metrics(req: HttpRequest) -> Result<HttpResponse, ApiError> {
+    let authorized = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(is_metrics_token);
+
+    if !authorized {
+        return Err(ApiError::Unauthorized);
+    }
+
+    let mut buffer = vec![];
+
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .map_err(|error| unexpected(ErrorSource::new(error)))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType(
+            TextEncoder::new().format_type().parse().unwrap(),
+        ))
+        .body(buffer))
+}
+
+// Any other method would add a series per spelling a client makes up.
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::PATCH => "PATCH",
+        Method::DELETE => "DELETE",
+        Method::HEAD => "HEAD",
+        Method::OPTIONS => "OPTIONS",
+        _ => "other",
+    }
+}
+
+// Routes are labelled with their pattern (`/api/v1/users/{login}`) so user
+// input can not blow up the number of series.
+#[derive(Default)]
+pub struct Metrics {}
+
+pub struct MetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+{
+    service: Rc<S>,
+}
+
+impl<S> Transform<S, ServiceRequest> for Metrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse;
+    type Error = WebActixError;
+    type Transform = MetricsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MetricsMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+impl<S> Service<ServiceRequest> for MetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse;
+    type Error = WebActixError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let started_at = Instant::now();
+
+        let method = method_label(req.method());
+
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let service_response = fut.await?;
+
+            let route = service_response
+                .request()
+                .match_pattern()
+                .unwrap_or_else(|| "unmatched".to_owned());
+
+            let status = service_response.status().as_u16().to_string();
+
+            let labels = [method, route.as_str(), status.as_str()];
+
+            HTTP_REQUESTS_TOTAL.with_label_values(&labels).inc();
+
+            HTTP_REQUEST_DURATION_SECONDS
+                .with_label_values(&labels)
+                .observe(started_at.elapsed().as_secs_f64());
+
+            Ok(service_response)
+        })
+    }
+}
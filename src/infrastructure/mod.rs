@@ -1,5 +1,21 @@
+pub mod app;
+pub mod config;
 pub mod constants;
 pub mod controllers;
+pub mod cors;
+pub mod csrf;
+pub mod errors;
+pub mod health;
+pub mod i18n;
+pub mod logging;
+pub mod metrics;
 pub mod models;
+pub mod openapi;
+pub mod request_id;
+pub mod shutdown;
+pub mod signing;
+pub mod telemetry;
+pub mod tls;
 pub mod user;
 pub mod utils;
+pub mod validation;
@@ -1,145 +1,235 @@
 use crate::core::user::service::{UserService, UserServiceGetOneError};
 use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
-use actix_web::{web::Data, Error as WebActixError, HttpResponse};
-use hmac::Hmac;
-use jwt::{Error as JwtError, Header, SignWithKey, Token, VerifyWithKey};
+use actix_web::{web::Data, Error as WebActixError};
+use jsonwebtoken::get_current_timestamp;
 use serde::{Deserialize, Serialize};
-use sha2::{digest::KeyInit, Sha256};
-use std::collections::BTreeMap;
-use std::env::var;
+use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::future::{ready, Future, Ready};
 use std::pin::Pin;
 use std::rc::Rc;
+use tracing::{field, instrument, Span};
+use utoipa::ToSchema;
 
-use crate::infrastructure::constants::ENV_CONFIG;
+use crate::infrastructure::{
+    constants::{ENV_CONFIG, KEY_RING},
+    errors::{unexpected, ApiError},
+    metrics::observe_auth_rejection,
+    signing::LEEWAY,
+};
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct ErrorDTO {
+    /// Stable machine-readable error code.
+    #[schema(example = "user_not_found")]
+    code: String,
+    /// Human-readable message in the request's locale.
     message: String,
 }
 
 impl ErrorDTO {
-    pub fn new(message: &str) -> Self {
+    pub fn new(code: &str, message: &str) -> Self {
         Self {
+            code: code.to_owned(),
             message: message.to_owned(),
         }
     }
 }
 
-pub struct EnvConfig {
-    jwt_secret: String,
-    jwt_domain: String,
-    access_control_allow_origin: String,
-    access_control_allow_methods: String,
-    access_control_allow_headers: String,
-    access_control_allow_credentials: String,
+#[derive(Serialize, ToSchema)]
+pub struct FieldErrorDTO {
+    #[schema(example = "login")]
+    field: String,
+    #[schema(example = "login_length")]
+    code: String,
+    message: String,
 }
 
-impl EnvConfig {
-    pub fn new() -> Self {
+impl FieldErrorDTO {
+    pub fn new(field: &str, code: &str, message: &str) -> Self {
         Self {
-            jwt_secret: var("JWT_SECRET").expect("ENV-variable `JWT_SECRET` must be set"),
-            jwt_domain: var("JWT_DOMAIN").expect("ENV-variable `JWT_DOMAIN` must be set"),
-            access_control_allow_origin: var("ACCESS_CONTROL_ALLOW_ORIGIN")
-                .expect("ENV-variable `ACCESS_CONTROL_ALLOW_ORIGIN` must be set"),
-            access_control_allow_methods: var("ACCESS_CONTROL_ALLOW_METHODS")
-                .expect("ENV-variable `ACCESS_CONTROL_ALLOW_METHODS` must be set"),
-            access_control_allow_headers: var("ACCESS_CONTROL_ALLOW_HEADERS")
-                .expect("ENV-variable `ACCESS_CONTROL_ALLOW_HEADERS` must be set"),
-            access_control_allow_credentials: var("ACCESS_CONTROL_ALLOW_CREDENTIALS")
-                .expect("ENV-variable `ACCESS_CONTROL_ALLOW_CREDENTIALS` must be set"),
+            field: field.to_owned(),
+            code: code.to_owned(),
+            message: message.to_owned(),
         }
     }
+}
 
-    pub fn check(&self) {}
-
-    pub fn clone_jwt_secret(&self) -> String {
-        self.jwt_secret.clone()
-    }
-
-    pub fn clone_jwt_domain(&self) -> String {
-        self.jwt_domain.clone()
-    }
-
-    pub fn clone_access_control_allow_origin(&self) -> String {
-        self.access_control_allow_origin.clone()
-    }
-
-    pub fn clone_access_control_allow_methods(&self) -> String {
-        self.access_control_allow_methods.clone()
+impl Display for FieldErrorDTO {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}: {}", self.field, self.message)
     }
+}
 
-    pub fn clone_access_control_allow_headers(&self) -> String {
-        self.access_control_allow_headers.clone()
-    }
+#[derive(Serialize, ToSchema)]
+pub struct ValidationErrorDTO {
+    #[schema(example = "validation_failed")]
+    code: String,
+    message: String,
+    errors: Vec<FieldErrorDTO>,
+}
 
-    pub fn clone_access_control_allow_credentials(&self) -> String {
-        self.access_control_allow_credentials.clone()
+impl ValidationErrorDTO {
+    pub fn new(code: &str, message: &str, errors: Vec<FieldErrorDTO>) -> Self {
+        Self {
+            code: code.to_owned(),
+            message: message.to_owned(),
+            errors,
+        }
     }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct JwtData {
     user_id: i32,
+    iat: u64,
+    exp: u64,
 }
 
 impl JwtData {
     pub fn new(user_id: i32) -> Self {
-        Self { user_id }
+        let iat = get_current_timestamp();
+
+        Self {
+            user_id,
+            iat,
+            exp: iat + ENV_CONFIG.get_jwt_ttl().as_secs(),
+        }
     }
 
     pub fn get_user_id(&self) -> i32 {
         self.user_id
     }
 
-    fn get_key() -> Hmac<Sha256> {
-        Hmac::<Sha256>::new_from_slice(ENV_CONFIG.clone_jwt_secret().as_bytes()).unwrap()
+    pub fn into_token(self) -> String {
+        KEY_RING.sign(&self)
     }
 
-    pub fn into_token(self) -> String {
-        let key = JwtData::get_key();
+    #[allow(clippy::result_unit_err)]
+    pub fn from_token_str(token_str: &str) -> Result<JwtData, ()> {
+        let jwt_data: JwtData = KEY_RING.verify(token_str)?;
 
-        let mut claims = BTreeMap::new();
+        if jwt_data.iat > get_current_timestamp() + LEEWAY || jwt_data.iat >= jwt_data.exp {
+            return Err(());
+        }
 
-        claims.insert("user_id", self.user_id);
+        Ok(jwt_data)
+    }
+}
 
-        let token_str = claims
-            .sign_with_key(&key)
-            .expect("Unexpected error while signing with key");
+#[derive(Serialize)]
+pub struct JwkDTO {
+    kty: String,
+    #[serde(rename = "use")]
+    usage: String,
+    alg: String,
+    kid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    e: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    crv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    x: Option<String>,
+}
 
-        token_str
+impl JwkDTO {
+    pub fn rsa(kid: &str, n: &str, e: &str) -> Self {
+        Self {
+            kty: "RSA".to_owned(),
+            usage: "sig".to_owned(),
+            alg: "RS256".to_owned(),
+            kid: kid.to_owned(),
+            n: Some(n.to_owned()),
+            e: Some(e.to_owned()),
+            crv: None,
+            x: None,
+        }
     }
 
-    pub fn from_token_str(token_str: &str) -> Result<JwtData, ()> {
-        let key = JwtData::get_key();
+    pub fn ed25519(kid: &str, x: &str) -> Self {
+        Self {
+            kty: "OKP".to_owned(),
+            usage: "sig".to_owned(),
+            alg: "EdDSA".to_owned(),
+            kid: kid.to_owned(),
+            n: None,
+            e: None,
+            crv: Some("Ed25519".to_owned()),
+            x: Some(x.to_owned()),
+        }
+    }
+}
 
-        let token: Result<Token<Header, BTreeMap<String, i32>, _>, JwtError> =
-            token_str.verify_with_key(&key);
+#[derive(Serialize)]
+pub struct JwksDTO {
+    keys: Vec<JwkDTO>,
+}
 
-        let token = token.unwrap();
+impl JwksDTO {
+    pub fn new(keys: Vec<JwkDTO>) -> Self {
+        Self { keys }
+    }
+}
 
-        let claims = token.claims();
+#[instrument(name = "auth_guard", skip_all, fields(user_id = field::Empty))]
+async fn authenticate(
+    req: &ServiceRequest,
+    user_service: &dyn UserService,
+    role: Option<&str>,
+) -> Result<(), ApiError> {
+    let jwt_data = req
+        .cookie("jwt")
+        .ok_or("missing_token")
+        .and_then(|jwt_cookie| {
+            JwtData::from_token_str(jwt_cookie.value()).map_err(|_| "invalid_token")
+        });
+
+    let jwt_data = match jwt_data {
+        Ok(jwt_data) => jwt_data,
+        Err(reason) => {
+            observe_auth_rejection(reason);
+
+            return Err(ApiError::Unauthorized);
+        }
+    };
 
-        let user_id = claims.get("user_id");
+    Span::current().record("user_id", jwt_data.get_user_id());
 
-        if let None = user_id {
-            return Err(());
+    let result = match user_service.get_one_by_id(jwt_data.get_user_id()).await {
+        Ok(user) if user.is_banned() => Err(("banned", ApiError::AccountBanned)),
+        Ok(user) if role.is_some_and(|role| !user.has_role(role)) => {
+            Err(("missing_role", ApiError::Forbidden))
         }
+        Ok(_) => Ok(()),
+        Err(UserServiceGetOneError::NotFound) => Err(("unknown_user", ApiError::Unauthorized)),
+        Err(UserServiceGetOneError::UnexpectedError(source)) => Err(("error", unexpected(source))),
+    };
 
-        let user_id = user_id.unwrap();
+    result.map_err(|(reason, error)| {
+        observe_auth_rejection(reason);
 
-        Ok(JwtData::new(*user_id))
-    }
+        error
+    })
 }
 
 #[derive(Default)]
-pub struct AuthGuard {}
+pub struct AuthGuard {
+    role: Option<&'static str>,
+}
+
+impl AuthGuard {
+    pub fn with_role(role: &'static str) -> Self {
+        Self { role: Some(role) }
+    }
+}
 
 pub struct AuthMiddleware<S>
 where
     S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
 {
     service: Rc<S>,
+    role: Option<&'static str>,
 }
 
 impl<S> Transform<S, ServiceRequest> for AuthGuard
@@ -156,6 +246,7 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ready(Ok(AuthMiddleware {
             service: Rc::new(service),
+            role: self.role,
         }))
     }
 }
@@ -179,49 +270,11 @@ where
             .expect("user_service is missing")
             .clone();
 
-        Box::pin(async move {
-            let jwt_cookie = req.cookie("jwt");
-
-            if jwt_cookie.is_none() {
-                return Ok(ServiceResponse::new(
-                    req.request().clone(),
-                    HttpResponse::Unauthorized().json(ErrorDTO::new("Вы не авторизованы")),
-                ));
-            }
-
-            let jwt_cookie = jwt_cookie.unwrap();
-
-            let jwt = jwt_cookie.value();
-
-            let jwt_data = JwtData::from_token_str(jwt);
+        let role = self.role;
 
-            if jwt_data.is_err() {
-                return Ok(ServiceResponse::new(
-                    req.request().clone(),
-                    HttpResponse::Unauthorized().json(ErrorDTO::new("Вы не авторизованы")),
-                ));
-            }
-
-            let jwt_data = jwt_data.unwrap();
-
-            let result = user_service.get_one_by_id(jwt_data.get_user_id()).await;
-
-            if let Err(error) = result {
-                match error {
-                    UserServiceGetOneError::NotFound => {
-                        return Ok(ServiceResponse::new(
-                            req.request().clone(),
-                            HttpResponse::Unauthorized().json(ErrorDTO::new("Вы не авторизованы")),
-                        ));
-                    }
-                    UserServiceGetOneError::UnexpectedError => {
-                        return Ok(ServiceResponse::new(
-                            req.request().clone(),
-                            HttpResponse::InternalServerError()
-                                .json(ErrorDTO::new("Внезапная ошибка")),
-                        ));
-                    }
-                }
+        Box::pin(async move {
+            if let Err(error) = authenticate(&req, user_service.as_ref(), role).await {
+                return Ok(req.error_response(error));
             }
 
             let res = service.call(req).await?;
@@ -0,0 +1,57 @@
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use super::models::ValidationErrorDTO;
+use super::user::controllers::{
+    __path_export_users, __path_get_profile, __path_get_user, __path_get_users,
+    __path_import_users, __path_login_user, __path_logout_user, __path_register_user,
+};
+use super::user::models::UserRecordDTO;
+
+struct SessionCookie;
+
+impl Modify for SessionCookie {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "session",
+                SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("jwt"))),
+            );
+        }
+    }
+}
+
+// Cargo.toml declares no license, which would otherwise show up as an empty
+// one.
+struct NoLicense;
+
+impl Modify for NoLicense {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi.info.license = None;
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(description = "User registration, sessions and lookup. Errors share one \
+        shape: a stable `code` and a `message` in the request's locale; \
+        `validation_failed` adds per-field `errors` (`ValidationErrorDTO`)."),
+    servers((url = "/api/v1")),
+    paths(
+        get_users,
+        get_profile,
+        register_user,
+        login_user,
+        logout_user,
+        get_user,
+        import_users,
+        export_users,
+    ),
+    components(schemas(ValidationErrorDTO, UserRecordDTO)),
+    modifiers(&SessionCookie, &NoLicense),
+    tags(
+        (name = "users", description = "Registration, sessions and user lookup"),
+        (name = "admin", description = "Bulk user transfer; needs the `admin` role"),
+    )
+)]
+pub struct ApiDoc;
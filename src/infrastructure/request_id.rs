@@ -0,0 +1,160 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{Error as WebActixError, HttpMessage};
+use opentelemetry::{global, trace::TraceContextExt};
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::time::Instant;
+use tracing::{field, info, info_span, Instrument, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use super::{telemetry::HeaderExtractor, utils::generate_salt};
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+// Longer ids, or ones with characters outside printable ASCII, are replaced
+// rather than propagated into logs.
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+#[derive(Debug, Clone)]
+pub struct RequestId(String);
+
+impl RequestId {
+    fn from_header(value: &HeaderValue) -> Option<Self> {
+        let value = value.to_str().ok()?;
+
+        if value.is_empty()
+            || value.len() > MAX_REQUEST_ID_LENGTH
+            || !value.chars().all(|c| c.is_ascii_graphic())
+        {
+            return None;
+        }
+
+        Some(Self(value.to_owned()))
+    }
+
+    fn generate() -> Self {
+        Self(generate_salt(32))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Default)]
+pub struct RequestTracing {}
+
+pub struct RequestTracingMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+{
+    service: Rc<S>,
+}
+
+impl<S> Transform<S, ServiceRequest> for RequestTracing
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse;
+    type Error = WebActixError;
+    type Transform = RequestTracingMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestTracingMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+impl<S> Service<ServiceRequest> for RequestTracingMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse;
+    type Error = WebActixError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let started_at = Instant::now();
+
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(RequestId::from_header)
+            .unwrap_or_else(RequestId::generate);
+
+        let span = info_span!(
+            "request",
+            request_id = request_id.as_str(),
+            method = %req.method(),
+            path = req.path(),
+            "otel.kind" = "server",
+            "otel.status_code" = field::Empty,
+            "http.route" = field::Empty,
+            "http.response.status_code" = field::Empty,
+        );
+
+        let parent = global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HeaderExtractor(req.headers()))
+        });
+
+        // Only fails when no OpenTelemetry layer is installed.
+        let _ = span.set_parent(parent);
+
+        let method = req.method().to_string();
+
+        req.extensions_mut().insert(request_id.clone());
+
+        let fut = span.in_scope(|| self.service.call(req));
+
+        Box::pin(
+            async move {
+                let mut service_response = fut.await?;
+
+                let span = Span::current();
+
+                let route = service_response
+                    .request()
+                    .match_pattern()
+                    .unwrap_or_else(|| "unmatched".to_owned());
+
+                let status = service_response.status();
+
+                // The OpenTelemetry span already exists by now, so `otel.name`
+                // would no longer apply.
+                span.context()
+                    .span()
+                    .update_name(format!("{} {}", method, route));
+                span.record("http.route", route.as_str());
+                span.record("http.response.status_code", status.as_u16());
+
+                if status.is_server_error() {
+                    span.record("otel.status_code", "ERROR");
+                }
+
+                info!(
+                    status = status.as_u16(),
+                    duration_ms = started_at.elapsed().as_millis() as u64,
+                    "request finished"
+                );
+
+                if let Ok(value) = HeaderValue::from_str(request_id.as_str()) {
+                    service_response
+                        .headers_mut()
+                        .insert(REQUEST_ID_HEADER, value);
+                }
+
+                Ok(service_response)
+            }
+            .instrument(span),
+        )
+    }
+}
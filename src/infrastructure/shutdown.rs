@@ -0,0 +1,176 @@
+use actix_web::dev::{
+    forward_ready, ServerHandle, Service, ServiceRequest, ServiceResponse, Transform,
+};
+use actix_web::rt::{signal, time::sleep};
+use actix_web::Error as WebActixError;
+use std::future::{ready, Future, Ready};
+use std::io;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tracing::info;
+
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+static IN_FLIGHT_REQUESTS: AtomicUsize = AtomicUsize::new(0);
+static DROPPED_REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::SeqCst)
+}
+
+pub fn dropped_requests() -> usize {
+    DROPPED_REQUESTS.load(Ordering::SeqCst)
+}
+
+// `SIGTERM` and `SIGINT`, registered up front so a failure aborts startup
+// instead of leaving a server no signal can stop.
+#[cfg(unix)]
+pub struct ShutdownSignals {
+    terminate: signal::unix::Signal,
+    interrupt: signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl ShutdownSignals {
+    pub fn register() -> io::Result<Self> {
+        use signal::unix::{signal, SignalKind};
+
+        Ok(Self {
+            terminate: signal(SignalKind::terminate())?,
+            interrupt: signal(SignalKind::interrupt())?,
+        })
+    }
+
+    async fn recv(&mut self) -> &'static str {
+        tokio::select! {
+            _ = self.terminate.recv() => "SIGTERM",
+            _ = self.interrupt.recv() => "SIGINT",
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub struct ShutdownSignals {
+    ctrl_c: signal::windows::CtrlC,
+}
+
+#[cfg(not(unix))]
+impl ShutdownSignals {
+    pub fn register() -> io::Result<Self> {
+        Ok(Self {
+            ctrl_c: signal::windows::ctrl_c()?,
+        })
+    }
+
+    async fn recv(&mut self) -> &'static str {
+        self.ctrl_c.recv().await;
+
+        "ctrl-c"
+    }
+}
+
+pub async fn stop_on_signal(server: ServerHandle, mut signals: ShutdownSignals, delay: Duration) {
+    let signal = signals.recv().await;
+
+    SHUTTING_DOWN.store(true, Ordering::SeqCst);
+
+    info!(
+        signal,
+        delay_secs = delay.as_secs(),
+        "shutdown requested, reporting not ready"
+    );
+
+    tokio::select! {
+        _ = sleep(delay) => {}
+        _ = signals.recv() => {}
+    }
+
+    info!(
+        in_flight = IN_FLIGHT_REQUESTS.load(Ordering::SeqCst),
+        "stopping server and draining requests"
+    );
+
+    server.stop(true).await;
+}
+
+struct InFlightGuard {
+    finished: bool,
+}
+
+impl InFlightGuard {
+    fn new() -> Self {
+        IN_FLIGHT_REQUESTS.fetch_add(1, Ordering::SeqCst);
+
+        Self { finished: false }
+    }
+
+    fn finish(mut self) {
+        self.finished = true;
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        IN_FLIGHT_REQUESTS.fetch_sub(1, Ordering::SeqCst);
+
+        // Outside of shutdown an unfinished request is a client that hung up.
+        if !self.finished && is_shutting_down() {
+            DROPPED_REQUESTS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct InFlightRequests {}
+
+pub struct InFlightRequestsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+{
+    service: Rc<S>,
+}
+
+impl<S> Transform<S, ServiceRequest> for InFlightRequests
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse;
+    type Error = WebActixError;
+    type Transform = InFlightRequestsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(InFlightRequestsMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+impl<S> Service<ServiceRequest> for InFlightRequestsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse;
+    type Error = WebActixError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let guard = InFlightGuard::new();
+
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let result = fut.await;
+
+            guard.finish();
+
+            result
+        })
+    }
+}
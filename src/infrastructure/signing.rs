@@ -0,0 +1,295 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ed25519_dalek::{SigningKey as Ed25519SigningKey, VerifyingKey as Ed25519VerifyingKey};
+use jsonwebtoken::{
+    decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use rsa::pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey};
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use rsa::traits::PublicKeyParts;
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs::read_to_string;
+use std::str::FromStr;
+
+use super::{
+    config::EnvConfig,
+    models::{JwkDTO, JwksDTO},
+};
+
+const MIN_RSA_KEY_BITS: usize = 2048;
+
+pub const LEEWAY: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JwtAlgorithm {
+    Hs256,
+    Rs256,
+    EdDsa,
+}
+
+impl From<JwtAlgorithm> for Algorithm {
+    fn from(algorithm: JwtAlgorithm) -> Self {
+        match algorithm {
+            JwtAlgorithm::Hs256 => Self::HS256,
+            JwtAlgorithm::Rs256 => Self::RS256,
+            JwtAlgorithm::EdDsa => Self::EdDSA,
+        }
+    }
+}
+
+impl FromStr for JwtAlgorithm {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "hs256" => Ok(Self::Hs256),
+            "rs256" => Ok(Self::Rs256),
+            "eddsa" => Ok(Self::EdDsa),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum PublicKey {
+    Secret,
+    Rsa { n: String, e: String },
+    Ed25519 { x: String },
+}
+
+impl PublicKey {
+    fn rsa(key: &RsaPublicKey) -> Result<Self, String> {
+        check_rsa_size(key.size() * 8)?;
+
+        Ok(Self::Rsa {
+            n: URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+            e: URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+        })
+    }
+
+    fn ed25519(key: &Ed25519VerifyingKey) -> Self {
+        Self::Ed25519 {
+            x: URL_SAFE_NO_PAD.encode(key.to_bytes()),
+        }
+    }
+
+    fn to_jwk(&self, key_id: &str) -> Option<JwkDTO> {
+        match self {
+            Self::Secret => None,
+            Self::Rsa { n, e } => Some(JwkDTO::rsa(key_id, n, e)),
+            Self::Ed25519 { x } => Some(JwkDTO::ed25519(key_id, x)),
+        }
+    }
+}
+
+pub struct SigningKey {
+    algorithm: JwtAlgorithm,
+    encoding_key: EncodingKey,
+    verifying_key: VerifyingKey,
+}
+
+impl SigningKey {
+    pub fn from_secret(secret: &str) -> Self {
+        Self {
+            algorithm: JwtAlgorithm::Hs256,
+            encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+            verifying_key: VerifyingKey::from_secret(secret),
+        }
+    }
+
+    pub fn from_pem(algorithm: JwtAlgorithm, pem: &str) -> Result<Self, String> {
+        let (encoding_key, public_key) = match algorithm {
+            JwtAlgorithm::Hs256 => return Err("HS256 uses `jwt.secret`, not a key file".to_owned()),
+            JwtAlgorithm::Rs256 => {
+                let key = RsaPrivateKey::from_pkcs8_pem(pem)
+                    .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+                    .map_err(|_| "is not an RSA private key in PEM format".to_owned())?;
+
+                let public_key = PublicKey::rsa(&key.to_public_key())?;
+
+                let encoding_key = EncodingKey::from_rsa_pem(pem.as_bytes())
+                    .map_err(|_| "is not an RSA private key in PEM format".to_owned())?;
+
+                (encoding_key, public_key)
+            }
+            JwtAlgorithm::EdDsa => {
+                let key = Ed25519SigningKey::from_pkcs8_pem(pem)
+                    .map_err(|_| "is not an Ed25519 private key in PEM format".to_owned())?;
+
+                let public_key = PublicKey::ed25519(&key.verifying_key());
+
+                let encoding_key = EncodingKey::from_ed_pem(pem.as_bytes())
+                    .map_err(|_| "is not an Ed25519 private key in PEM format".to_owned())?;
+
+                (encoding_key, public_key)
+            }
+        };
+
+        Ok(Self {
+            algorithm,
+            encoding_key,
+            verifying_key: VerifyingKey::from_public_key(algorithm, public_key)?,
+        })
+    }
+
+    pub fn algorithm(&self) -> JwtAlgorithm {
+        self.algorithm
+    }
+}
+
+#[derive(Clone)]
+pub struct VerifyingKey {
+    algorithm: JwtAlgorithm,
+    decoding_key: DecodingKey,
+    public_key: PublicKey,
+}
+
+impl VerifyingKey {
+    fn from_secret(secret: &str) -> Self {
+        Self {
+            algorithm: JwtAlgorithm::Hs256,
+            decoding_key: DecodingKey::from_secret(secret.as_bytes()),
+            public_key: PublicKey::Secret,
+        }
+    }
+
+    fn from_public_key(algorithm: JwtAlgorithm, public_key: PublicKey) -> Result<Self, String> {
+        let decoding_key = match &public_key {
+            PublicKey::Secret => unreachable!("secrets are never parsed as public keys"),
+            PublicKey::Rsa { n, e } => DecodingKey::from_rsa_components(n, e),
+            PublicKey::Ed25519 { x } => DecodingKey::from_ed_components(x),
+        }
+        .map_err(|error| format!("can not be used for verification: {}", error))?;
+
+        Ok(Self {
+            algorithm,
+            decoding_key,
+            public_key,
+        })
+    }
+
+    pub fn from_public_pem(pem: &str) -> Result<Self, String> {
+        if let Ok(key) =
+            RsaPublicKey::from_public_key_pem(pem).or_else(|_| RsaPublicKey::from_pkcs1_pem(pem))
+        {
+            return Self::from_public_key(JwtAlgorithm::Rs256, PublicKey::rsa(&key)?);
+        }
+
+        let key = Ed25519VerifyingKey::from_public_key_pem(pem)
+            .map_err(|_| "is not an RSA or Ed25519 public key in PEM format".to_owned())?;
+
+        Self::from_public_key(JwtAlgorithm::EdDsa, PublicKey::ed25519(&key))
+    }
+
+    pub fn algorithm(&self) -> JwtAlgorithm {
+        self.algorithm
+    }
+}
+
+fn check_rsa_size(bits: usize) -> Result<(), String> {
+    if bits < MIN_RSA_KEY_BITS {
+        return Err(format!(
+            "is a {}-bit RSA key, at least {} bits are required",
+            bits, MIN_RSA_KEY_BITS
+        ));
+    }
+
+    Ok(())
+}
+
+fn read_pem(path: &str) -> Result<String, String> {
+    read_to_string(path).map_err(|error| format!("can not read `{}`: {}", path, error))
+}
+
+pub struct KeyRing {
+    key_id: String,
+    signing_key: SigningKey,
+    verifying_keys: Vec<(String, VerifyingKey)>,
+}
+
+impl KeyRing {
+    pub fn load(config: &EnvConfig) -> Result<Self, Vec<(&'static str, String)>> {
+        let mut errors = vec![];
+
+        let signing_key = match config.get_jwt_algorithm() {
+            JwtAlgorithm::Hs256 => Some(SigningKey::from_secret(&config.clone_jwt_secret())),
+            algorithm => read_pem(&config.clone_jwt_private_key_file())
+                .and_then(|pem| SigningKey::from_pem(algorithm, &pem))
+                .map_err(|message| errors.push(("jwt.private_key_file", message)))
+                .ok(),
+        };
+
+        let mut verifying_keys: Vec<(String, VerifyingKey)> = config
+            .clone_jwt_previous_keys()
+            .into_iter()
+            .map(|(key_id, secret)| (key_id, VerifyingKey::from_secret(&secret)))
+            .collect();
+
+        for (key_id, path) in config.clone_jwt_previous_public_keys() {
+            match read_pem(&path).and_then(|pem| VerifyingKey::from_public_pem(&pem)) {
+                Ok(key) => verifying_keys.push((key_id, key)),
+                Err(message) => errors.push((
+                    "jwt.previous_public_keys",
+                    format!("key `{}` {}", key_id, message),
+                )),
+            }
+        }
+
+        match signing_key {
+            Some(signing_key) if errors.is_empty() => {
+                let key_id = config.clone_jwt_key_id();
+
+                verifying_keys.insert(0, (key_id.clone(), signing_key.verifying_key.clone()));
+
+                Ok(Self {
+                    key_id,
+                    signing_key,
+                    verifying_keys,
+                })
+            }
+            _ => Err(errors),
+        }
+    }
+
+    pub fn sign<T: Serialize>(&self, claims: &T) -> String {
+        let mut header = Header::new(self.signing_key.algorithm().into());
+
+        header.kid = Some(self.key_id.clone());
+
+        encode(&header, claims, &self.signing_key.encoding_key)
+            .expect("Unexpected error while signing with key")
+    }
+
+    // Tokens without `kid` predate key rotation. Each key only accepts its
+    // own algorithm, so an RSA public key can never be used as an HMAC secret.
+    #[allow(clippy::result_unit_err)]
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Result<T, ()> {
+        let header = decode_header(token).map_err(|_| ())?;
+
+        let key_id = header.kid.as_deref().unwrap_or(&self.key_id);
+
+        let key = self
+            .verifying_keys
+            .iter()
+            .find(|(id, _)| id == key_id)
+            .map(|(_, key)| key)
+            .ok_or(())?;
+
+        let mut validation = Validation::new(key.algorithm().into());
+
+        validation.leeway = LEEWAY;
+
+        decode::<T>(token, &key.decoding_key, &validation)
+            .map(|data| data.claims)
+            .map_err(|_| ())
+    }
+
+    pub fn to_jwks(&self) -> JwksDTO {
+        JwksDTO::new(
+            self.verifying_keys
+                .iter()
+                .filter_map(|(key_id, key)| key.public_key.to_jwk(key_id))
+                .collect(),
+        )
+    }
+}
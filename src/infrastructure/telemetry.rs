@@ -0,0 +1,118 @@
+use actix_web::http::header::HeaderMap;
+use chrono::{DateTime, SecondsFormat, Utc};
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{SpanExporter as OtlpSpanExporter, WithExportConfig};
+use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
+use opentelemetry_sdk::trace::{SdkTracerProvider, SpanData, SpanExporter, Tracer};
+use opentelemetry_sdk::Resource;
+use serde_json::{json, Map, Value};
+use std::io::{stdout, Write};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceExporter {
+    None,
+    Otlp,
+    Stdout,
+}
+
+impl FromStr for TraceExporter {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "otlp" => Ok(Self::Otlp),
+            "stdout" => Ok(Self::Stdout),
+            _ => Err(()),
+        }
+    }
+}
+
+// The caller shuts the provider down before exiting so buffered spans are
+// flushed.
+pub fn tracer_provider(
+    exporter: TraceExporter,
+    otlp_endpoint: &str,
+    service_name: &str,
+) -> Result<Option<SdkTracerProvider>, String> {
+    let builder = SdkTracerProvider::builder().with_resource(
+        Resource::builder()
+            .with_service_name(service_name.to_owned())
+            .build(),
+    );
+
+    let builder = match exporter {
+        TraceExporter::None => return Ok(None),
+        TraceExporter::Otlp => builder.with_batch_exporter(
+            OtlpSpanExporter::builder()
+                .with_http()
+                .with_endpoint(otlp_endpoint)
+                .build()
+                .map_err(|error| error.to_string())?,
+        ),
+        TraceExporter::Stdout => builder.with_batch_exporter(StdoutSpanExporter {}),
+    };
+
+    Ok(Some(builder.build()))
+}
+
+pub fn tracer(provider: &SdkTracerProvider) -> Tracer {
+    provider.tracer(env!("CARGO_PKG_NAME"))
+}
+
+pub struct HeaderExtractor<'a>(pub &'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+fn timestamp(time: std::time::SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+#[derive(Debug)]
+struct StdoutSpanExporter {}
+
+impl SpanExporter for StdoutSpanExporter {
+    async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+        let mut out = stdout().lock();
+
+        for span in batch {
+            let attributes: Map<String, Value> = span
+                .attributes
+                .iter()
+                .map(|attribute| {
+                    (
+                        attribute.key.to_string(),
+                        Value::String(attribute.value.to_string()),
+                    )
+                })
+                .collect();
+
+            let line = json!({
+                "trace_id": span.span_context.trace_id().to_string(),
+                "span_id": span.span_context.span_id().to_string(),
+                "parent_span_id": span.parent_span_id.to_string(),
+                "name": span.name,
+                "kind": format!("{:?}", span.span_kind),
+                "start": timestamp(span.start_time),
+                "end": timestamp(span.end_time),
+                "status": format!("{:?}", span.status),
+                "attributes": attributes,
+            });
+
+            writeln!(out, "{}", line)
+                .map_err(|error| OTelSdkError::InternalFailure(error.to_string()))?;
+        }
+
+        Ok(())
+    }
+}
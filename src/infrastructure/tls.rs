@@ -0,0 +1,213 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header;
+use actix_web::{Error as WebActixError, HttpResponse};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::{any_supported_type, CertifiedKey};
+use rustls::{Certificate, PrivateKey, ServerConfig};
+use rustls_pemfile::{read_all, Item};
+use std::fs::{metadata, File};
+use std::future::{ready, Future, Ready};
+use std::io::BufReader;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+use super::constants::ENV_CONFIG;
+
+const RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+static CERT_RELOAD_FAILED: AtomicBool = AtomicBool::new(false);
+
+pub fn is_cert_reload_failed() -> bool {
+    CERT_RELOAD_FAILED.load(Ordering::SeqCst)
+}
+
+fn read_pem(path: &str) -> Result<Vec<Item>, String> {
+    File::open(path)
+        .and_then(|file| read_all(&mut BufReader::new(file)))
+        .map_err(|error| format!("can not read `{}`: {}", path, error))
+}
+
+pub fn load_certified_key(cert_file: &str, key_file: &str) -> Result<CertifiedKey, String> {
+    let certs: Vec<Certificate> = read_pem(cert_file)?
+        .into_iter()
+        .filter_map(|item| match item {
+            Item::X509Certificate(der) => Some(Certificate(der)),
+            _ => None,
+        })
+        .collect();
+
+    if certs.is_empty() {
+        return Err(format!("`{}` contains no certificate", cert_file));
+    }
+
+    let key = read_pem(key_file)?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(der) | Item::RSAKey(der) | Item::ECKey(der) => Some(PrivateKey(der)),
+            _ => None,
+        })
+        .ok_or_else(|| format!("`{}` contains no private key", key_file))?;
+
+    let key = any_supported_type(&key)
+        .map_err(|_| format!("`{}` holds an unsupported private key type", key_file))?;
+
+    Ok(CertifiedKey::new(certs, key))
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+pub struct ReloadingCertResolver {
+    cert_file: String,
+    key_file: String,
+    certified_key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl ReloadingCertResolver {
+    pub fn new(cert_file: String, key_file: String) -> Result<Arc<Self>, String> {
+        let certified_key = load_certified_key(&cert_file, &key_file)?;
+
+        Ok(Arc::new(Self {
+            cert_file,
+            key_file,
+            certified_key: RwLock::new(Arc::new(certified_key)),
+        }))
+    }
+
+    // Polls the files' modification times in a background thread. A file
+    // that fails to load keeps the previous certificate in place; it is
+    // retried on the next change.
+    pub fn watch(self: &Arc<Self>) {
+        let resolver = self.clone();
+
+        thread::spawn(move || {
+            let mut last_modified = (modified(&resolver.cert_file), modified(&resolver.key_file));
+
+            loop {
+                thread::sleep(RELOAD_INTERVAL);
+
+                let current = (modified(&resolver.cert_file), modified(&resolver.key_file));
+
+                if current == last_modified {
+                    continue;
+                }
+
+                last_modified = current;
+
+                match load_certified_key(&resolver.cert_file, &resolver.key_file) {
+                    Ok(certified_key) => {
+                        *resolver
+                            .certified_key
+                            .write()
+                            .unwrap_or_else(PoisonError::into_inner) = Arc::new(certified_key);
+
+                        CERT_RELOAD_FAILED.store(false, Ordering::SeqCst);
+
+                        info!(cert_file = resolver.cert_file, "reloaded TLS certificate");
+                    }
+                    Err(message) => {
+                        CERT_RELOAD_FAILED.store(true, Ordering::SeqCst);
+
+                        warn!(error = message, "keeping the previous TLS certificate")
+                    }
+                }
+            }
+        });
+    }
+}
+
+impl ResolvesServerCert for ReloadingCertResolver {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(
+            self.certified_key
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone(),
+        )
+    }
+}
+
+pub fn server_config(resolver: Arc<ReloadingCertResolver>) -> ServerConfig {
+    ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(resolver)
+}
+
+#[derive(Default)]
+pub struct HttpsRedirect {}
+
+pub struct HttpsRedirectMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+{
+    service: Rc<S>,
+}
+
+impl<S> Transform<S, ServiceRequest> for HttpsRedirect
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse;
+    type Error = WebActixError;
+    type Transform = HttpsRedirectMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(HttpsRedirectMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+impl<S> Service<ServiceRequest> for HttpsRedirectMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = WebActixError> + 'static,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse;
+    type Error = WebActixError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if req.app_config().secure() {
+            return Box::pin(self.service.call(req));
+        }
+
+        let host = req.connection_info().host().to_owned();
+
+        // Drop the plain HTTP port, keeping IPv6 literals such as `[::1]` intact.
+        let host = match host.rsplit_once(':') {
+            Some((name, _)) if !host.ends_with(']') => name.to_owned(),
+            _ => host,
+        };
+
+        let port = match ENV_CONFIG.get_port() {
+            443 => String::new(),
+            port => format!(":{}", port),
+        };
+
+        let path = req
+            .uri()
+            .path_and_query()
+            .map_or("/", |path_and_query| path_and_query.as_str());
+
+        let location = format!("https://{}{}{}", host, port, path);
+
+        let response = HttpResponse::PermanentRedirect()
+            .insert_header((header::LOCATION, location))
+            .finish();
+
+        Box::pin(ready(Ok(req.into_response(response))))
+    }
+}
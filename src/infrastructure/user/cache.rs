@@ -0,0 +1,243 @@
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tracing::instrument;
+
+use crate::core::user::{
+    login::login_key,
+    models::{User, UserQuery, UserRecord, UsersPage},
+    repository::{
+        UserRepository, UserRepositoryFlushError, UserRepositoryHealthCheckError,
+        UserRepositoryInsertError, UserRepositorySelectManyError, UserRepositorySelectOneError,
+        UserRepositoryUpdateError,
+    },
+};
+use crate::infrastructure::metrics::{observe_user_cache_eviction, observe_user_cache_lookup};
+
+struct Entry {
+    user: User,
+    login_key: String,
+    expires_at: Instant,
+}
+
+#[derive(Default)]
+struct Entries {
+    by_id: HashMap<i32, Entry>,
+    by_login: HashMap<String, i32>,
+    // Ids as they were cached. Every entry lives as long, so this is also
+    // the order they expire in; ids removed since are skipped.
+    order: VecDeque<(i32, Instant)>,
+    // Bumped by every update, see `CachedUserRepository::put`.
+    updates: u64,
+}
+
+impl Entries {
+    fn get(&self, id: i32, now: Instant) -> Option<&User> {
+        self.by_id
+            .get(&id)
+            .filter(|entry| entry.expires_at > now)
+            .map(|entry| &entry.user)
+    }
+
+    fn remove(&mut self, id: i32) -> bool {
+        match self.by_id.remove(&id) {
+            Some(entry) => {
+                self.by_login.remove(&entry.login_key);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn make_room(&mut self, now: Instant, capacity: usize) {
+        while let Some(&(id, expires_at)) = self.order.front() {
+            let is_current = self
+                .by_id
+                .get(&id)
+                .is_some_and(|entry| entry.expires_at == expires_at);
+
+            let is_expired = expires_at <= now;
+
+            if is_current && !is_expired && self.by_id.len() < capacity {
+                break;
+            }
+
+            self.order.pop_front();
+
+            if is_current {
+                self.remove(id);
+
+                observe_user_cache_eviction(if is_expired { "expired" } else { "capacity" });
+            }
+        }
+    }
+}
+
+// Writes made by another process, e.g. `oped-admin` against a shared
+// database, show up once the cached copy expires.
+pub struct CachedUserRepository {
+    inner: Arc<dyn UserRepository>,
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+impl CachedUserRepository {
+    pub fn new(inner: Arc<dyn UserRepository>, ttl: Duration, capacity: usize) -> Self {
+        Self {
+            inner,
+            ttl,
+            capacity,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    fn lock_entries(&self) -> MutexGuard<'_, Entries> {
+        match self.entries.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    // Caches `user` as read from the wrapped repository after `updates`
+    // updates. An update that finished since may have replaced it, so it is
+    // dropped then rather than served stale until it expires.
+    fn put(&self, user: &User, updates: u64) {
+        let mut entries = self.lock_entries();
+
+        if entries.updates != updates {
+            return;
+        }
+
+        let now = Instant::now();
+
+        let id = user.get_id();
+
+        entries.remove(id);
+
+        entries.make_room(now, self.capacity);
+
+        let key = login_key(user.clone_login().as_str());
+
+        let expires_at = now + self.ttl;
+
+        entries.by_login.insert(key.clone(), id);
+
+        entries.by_id.insert(
+            id,
+            Entry {
+                user: user.clone(),
+                login_key: key,
+                expires_at,
+            },
+        );
+
+        entries.order.push_back((id, expires_at));
+    }
+}
+
+#[async_trait]
+impl UserRepository for CachedUserRepository {
+    async fn select_many(
+        &self,
+        query: UserQuery,
+    ) -> Result<UsersPage, UserRepositorySelectManyError> {
+        self.inner.select_many(query).await
+    }
+
+    #[instrument(name = "user_cache.select_one_by_id", skip(self))]
+    async fn select_one_by_id(&self, id: i32) -> Result<User, UserRepositorySelectOneError> {
+        let updates = {
+            let entries = self.lock_entries();
+
+            if let Some(user) = entries.get(id, Instant::now()) {
+                observe_user_cache_lookup("id", true);
+
+                return Ok(user.clone());
+            }
+
+            entries.updates
+        };
+
+        observe_user_cache_lookup("id", false);
+
+        let user = self.inner.select_one_by_id(id).await?;
+
+        self.put(&user, updates);
+
+        Ok(user)
+    }
+
+    #[instrument(name = "user_cache.select_one_by_login", skip_all)]
+    async fn select_one_by_login(
+        &self,
+        login: String,
+    ) -> Result<User, UserRepositorySelectOneError> {
+        let key = login_key(login.as_str());
+
+        let updates = {
+            let entries = self.lock_entries();
+
+            let cached = entries
+                .by_login
+                .get(&key)
+                .and_then(|id| entries.get(*id, Instant::now()));
+
+            if let Some(user) = cached {
+                observe_user_cache_lookup("login", true);
+
+                return Ok(user.clone());
+            }
+
+            entries.updates
+        };
+
+        observe_user_cache_lookup("login", false);
+
+        let user = self.inner.select_one_by_login(login).await?;
+
+        self.put(&user, updates);
+
+        Ok(user)
+    }
+
+    async fn insert(
+        &self,
+        login: String,
+        hash: String,
+        salt: String,
+    ) -> Result<i32, UserRepositoryInsertError> {
+        // Misses are not cached, so a new user never hides behind one.
+        self.inner.insert(login, hash, salt).await
+    }
+
+    async fn insert_record(&self, record: UserRecord) -> Result<i32, UserRepositoryInsertError> {
+        self.inner.insert_record(record).await
+    }
+
+    async fn update(&self, user: User) -> Result<(), UserRepositoryUpdateError> {
+        let id = user.get_id();
+
+        let result = self.inner.update(user).await;
+
+        // Evicts even when the update failed: the stored user is unknown.
+        let mut entries = self.lock_entries();
+
+        entries.updates += 1;
+
+        if entries.remove(id) {
+            observe_user_cache_eviction("updated");
+        }
+
+        result
+    }
+
+    async fn health_check(&self) -> Result<(), UserRepositoryHealthCheckError> {
+        self.inner.health_check().await
+    }
+
+    async fn flush(&self) -> Result<(), UserRepositoryFlushError> {
+        self.inner.flush().await
+    }
+}
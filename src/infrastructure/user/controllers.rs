@@ -1,176 +1,239 @@
 use actix_web::cookie::time::Duration;
 use actix_web::{
     cookie::Cookie,
-    web::{get, post, scope, Data, Json, ServiceConfig},
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    web::{get, post, scope, Data, PayloadConfig, Query, ServiceConfig},
     HttpRequest, HttpResponse, Responder,
 };
 
-use crate::core::user::service::{
-    UserService, UserServiceGetAllError, UserServiceGetOneError, UserServiceLoginError,
-    UserServiceRegisterError,
-};
+use crate::core::user::service::UserService;
 use crate::infrastructure::{
     constants::ENV_CONFIG,
+    csrf::Csrf,
+    errors::ApiError,
+    i18n::Locale,
     models::{AuthGuard, ErrorDTO, JwtData},
+    validation::{ValidatedJson, ValidatedQuery},
 };
 
 use super::models::{
-    GetProfileResDTO, GetUserResDTO, LoginUserReqDTO, LoginUserResDTO, LogoutUserResDTO,
+    ExportUsersReqDTO, GetProfileResDTO, GetUserResDTO, GetUsersReqDTO, GetUsersResDTO,
+    ImportReportDTO, ImportUsersReqDTO, LoginUserReqDTO, LoginUserResDTO, LogoutUserResDTO,
     RegisterUserReqDTO,
 };
+use super::transfer;
+
+pub const ADMIN_ROLE: &str = "admin";
+
+const IMPORT_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+#[utoipa::path(
+    get,
+    path = "/users",
+    tag = "users",
+    params(GetUsersReqDTO),
+    responses(
+        (status = 200, description = "One page of users", body = GetUsersResDTO),
+        (status = 400, description = "`validation_failed` or `malformed_request`", body = ErrorDTO),
+        (status = 500, description = "`unexpected_error`", body = ErrorDTO),
+    )
+)]
+pub async fn get_users(
+    user_service: Data<dyn UserService>,
+    req: HttpRequest,
+    dto: ValidatedQuery<GetUsersReqDTO>,
+) -> Result<HttpResponse, ApiError> {
+    let dto = dto.into_inner();
+
+    let page = dto.page;
+
+    let per_page = dto.per_page;
+
+    let users = user_service.get_many(dto.clone().into()).await?;
+
+    let total = users.get_total();
+
+    let next = if page * per_page < total {
+        let next_dto = GetUsersReqDTO {
+            page: page + 1,
+            ..dto
+        };
+
+        serde_urlencoded::to_string(next_dto)
+            .ok()
+            .map(|query| format!("{}?{}", req.path(), query))
+    } else {
+        None
+    };
+
+    let items: Vec<GetUserResDTO> = users.into_users().into();
 
-pub async fn get_users(user_service: Data<dyn UserService>) -> impl Responder {
-    let users = user_service.get_all().await;
-
-    match users {
-        Ok(users) => {
-            let dto: Vec<GetUserResDTO> = users.into();
-
-            HttpResponse::Ok().json(dto)
-        }
-        Err(error) => match error {
-            UserServiceGetAllError::UnexpectedError => {
-                HttpResponse::InternalServerError().json(ErrorDTO::new("Внезапная ошибка"))
-            }
-        },
-    }
+    Ok(HttpResponse::Ok().json(GetUsersResDTO::new(items, total, page, per_page, next)))
 }
 
-pub async fn get_user(user_service: Data<dyn UserService>, req: HttpRequest) -> impl Responder {
+#[utoipa::path(
+    get,
+    path = "/users/{login}",
+    tag = "users",
+    params(("login" = String, Path, description = "Login, compared case-insensitively")),
+    responses(
+        (status = 200, description = "The user", body = GetUserResDTO),
+        (status = 404, description = "`user_not_found`", body = ErrorDTO),
+        (status = 500, description = "`unexpected_error`", body = ErrorDTO),
+    )
+)]
+pub async fn get_user(
+    user_service: Data<dyn UserService>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
     let login = req.match_info().query("login");
 
-    let user = user_service.get_one_by_login(login.to_owned()).await;
-
-    match user {
-        Ok(user) => {
-            let dto: GetUserResDTO = user.into();
-
-            HttpResponse::Ok().json(dto)
-        }
-        Err(error) => match error {
-            UserServiceGetOneError::NotFound => {
-                HttpResponse::NotFound().json(ErrorDTO::new("Пользователь не найден"))
-            }
-            UserServiceGetOneError::UnexpectedError => {
-                HttpResponse::InternalServerError().json(ErrorDTO::new("Внезапная ошибка"))
-            }
-        },
-    }
+    let user = user_service.get_one_by_login(login.to_owned()).await?;
+
+    let dto: GetUserResDTO = user.into();
+
+    Ok(HttpResponse::Ok().json(dto))
 }
 
-pub async fn get_profile(user_service: Data<dyn UserService>, req: HttpRequest) -> impl Responder {
-    let jwt_cookie = req.cookie("jwt").unwrap();
+#[utoipa::path(
+    get,
+    path = "/users/profile",
+    tag = "users",
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "The signed-in user", body = GetProfileResDTO),
+        (status = 401, description = "`unauthorized`", body = ErrorDTO),
+        (status = 403, description = "`account_banned`", body = ErrorDTO),
+        (status = 500, description = "`unexpected_error`", body = ErrorDTO),
+    )
+)]
+pub async fn get_profile(
+    user_service: Data<dyn UserService>,
+    req: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let jwt_cookie = req.cookie("jwt").ok_or(ApiError::Unauthorized)?;
 
     let jwt = jwt_cookie.value();
 
-    let jwt_data = JwtData::from_token_str(jwt).unwrap();
-
-    let user = user_service.get_one_by_id(jwt_data.get_user_id()).await;
-
-    match user {
-        Ok(user) => {
-            let dto: GetProfileResDTO = user.into();
-
-            HttpResponse::Ok().json(dto)
-        }
-        Err(error) => match error {
-            UserServiceGetOneError::NotFound => {
-                HttpResponse::NotFound().json(ErrorDTO::new("Пользователь не найден"))
-            }
-            UserServiceGetOneError::UnexpectedError => {
-                HttpResponse::InternalServerError().json(ErrorDTO::new("Внезапная ошибка"))
-            }
-        },
-    }
+    let jwt_data = JwtData::from_token_str(jwt).map_err(|_| ApiError::Unauthorized)?;
+
+    let user = user_service.get_one_by_id(jwt_data.get_user_id()).await?;
+
+    let dto: GetProfileResDTO = user.into();
+
+    Ok(HttpResponse::Ok().json(dto))
 }
 
+#[utoipa::path(
+    post,
+    path = "/users/registration",
+    tag = "users",
+    request_body = RegisterUserReqDTO,
+    responses(
+        (status = 200, description = "The registered user", body = GetUserResDTO),
+        (
+            status = 400,
+            description = "`validation_failed`, `malformed_request`, `login_already_used`, \
+                `login_not_allowed` or `login_mixed_script`",
+            body = ErrorDTO
+        ),
+        (status = 403, description = "`cross_site_request`", body = ErrorDTO),
+        (status = 500, description = "`unexpected_error`", body = ErrorDTO),
+    )
+)]
 pub async fn register_user(
     user_service: Data<dyn UserService>,
-    dto: Json<RegisterUserReqDTO>,
-) -> impl Responder {
+    dto: ValidatedJson<RegisterUserReqDTO>,
+) -> Result<HttpResponse, ApiError> {
     let dto = dto.into_inner();
 
-    let login = dto.login;
-
-    let password = dto.password;
-
-    if login.len() < 3 || login.len() > 30 {
-        return HttpResponse::BadRequest().json(ErrorDTO::new("Длина логина: 3-30 символов"));
-    }
+    let login = dto.login.unwrap_or_default();
 
-    if password.len() < 3 || password.len() > 30 {
-        return HttpResponse::BadRequest().json(ErrorDTO::new("Длина пароля: 3-30 символов"));
-    }
+    let password = dto.password.unwrap_or_default();
 
-    let user = user_service.register(login, password).await;
+    let user = user_service.register(login, password).await?;
 
-    match user {
-        Ok(user) => {
-            let res_dto: GetUserResDTO = user.into();
+    let res_dto: GetUserResDTO = user.into();
 
-            HttpResponse::Ok().json(res_dto)
-        }
-        Err(error) => match error {
-            UserServiceRegisterError::LoginAlreadyUsed => {
-                HttpResponse::BadRequest().json(ErrorDTO::new("Данный логин уже используется"))
-            }
-            UserServiceRegisterError::UnexpectedError => {
-                HttpResponse::InternalServerError().json(ErrorDTO::new("Внезапная ошибка"))
-            }
-        },
-    }
+    Ok(HttpResponse::Ok().json(res_dto))
 }
 
+#[utoipa::path(
+    post,
+    path = "/users/login",
+    tag = "users",
+    request_body = LoginUserReqDTO,
+    responses(
+        (
+            status = 200,
+            description = "Signed in; the session token is set as the `jwt` cookie",
+            body = LoginUserResDTO,
+            headers(("Set-Cookie" = String, description = "`jwt=<token>; HttpOnly; Secure`"))
+        ),
+        (
+            status = 400,
+            description = "`validation_failed`, `malformed_request` or `wrong_credentials`",
+            body = ErrorDTO
+        ),
+        (status = 403, description = "`cross_site_request` or `account_banned`", body = ErrorDTO),
+        (status = 500, description = "`unexpected_error`", body = ErrorDTO),
+    )
+)]
 pub async fn login_user(
     user_service: Data<dyn UserService>,
-    dto: Json<LoginUserReqDTO>,
-) -> impl Responder {
+    dto: ValidatedJson<LoginUserReqDTO>,
+) -> Result<HttpResponse, ApiError> {
     let dto = dto.into_inner();
 
-    let login = dto.login;
-
-    let password = dto.password;
-
-    if login.len() < 3 || login.len() > 30 {
-        return HttpResponse::BadRequest().json(ErrorDTO::new("Длина логина: 3-30 символов"));
-    }
-
-    if password.len() < 3 || password.len() > 30 {
-        return HttpResponse::BadRequest().json(ErrorDTO::new("Длина пароля: 3-30 символов"));
-    }
-
-    let token = user_service.login(login, password).await;
-
-    match token {
-        Ok(token) => {
-            let cookie = Cookie::build("jwt", token)
-                .domain(ENV_CONFIG.clone_jwt_domain())
-                .path("/")
-                .secure(true)
-                .http_only(true)
-                .finish();
-
-            HttpResponse::Ok()
-                .cookie(cookie)
-                .json(LoginUserResDTO::default())
-        }
-        Err(error) => match error {
-            UserServiceLoginError::NotFound | UserServiceLoginError::WrongPassword => {
-                HttpResponse::BadRequest().json(ErrorDTO::new("Неверный логин или пароль"))
-            }
-            UserServiceLoginError::UnexpectedError => {
-                HttpResponse::InternalServerError().json(ErrorDTO::new("Внезапная ошибка"))
-            }
-        },
-    }
+    let login = dto.login.unwrap_or_default();
+
+    let password = dto.password.unwrap_or_default();
+
+    let token = user_service.login(login, password).await?;
+
+    let cookie = Cookie::build("jwt", token)
+        .domain(ENV_CONFIG.clone_jwt_domain())
+        .path("/")
+        .secure(true)
+        .http_only(true)
+        .same_site(ENV_CONFIG.get_jwt_same_site())
+        .max_age(Duration::seconds(
+            ENV_CONFIG
+                .get_jwt_ttl()
+                .as_secs()
+                .try_into()
+                .unwrap_or(i64::MAX),
+        ))
+        .finish();
+
+    Ok(HttpResponse::Ok()
+        .cookie(cookie)
+        .json(LoginUserResDTO::default()))
 }
 
+#[utoipa::path(
+    post,
+    path = "/users/logout",
+    tag = "users",
+    security(("session" = [])),
+    responses(
+        (
+            status = 200,
+            description = "Signed out; the `jwt` cookie is cleared",
+            body = LogoutUserResDTO
+        ),
+        (status = 401, description = "`unauthorized`", body = ErrorDTO),
+        (status = 403, description = "`cross_site_request` or `account_banned`", body = ErrorDTO),
+        (status = 500, description = "`unexpected_error`", body = ErrorDTO),
+    )
+)]
 pub async fn logout_user() -> impl Responder {
     let cookie = Cookie::build("jwt", "")
         .domain(ENV_CONFIG.clone_jwt_domain())
         .path("/")
         .secure(true)
         .http_only(true)
+        .same_site(ENV_CONFIG.get_jwt_same_site())
         .max_age(Duration::ZERO)
         .finish();
 
@@ -179,14 +242,112 @@ pub async fn logout_user() -> impl Responder {
         .json(LogoutUserResDTO::default())
 }
 
+#[utoipa::path(
+    post,
+    path = "/admin/users/import",
+    tag = "admin",
+    security(("session" = [])),
+    params(ImportUsersReqDTO),
+    request_body(
+        description = "`UserRecordDTO` rows as JSON Lines, or CSV with a header row",
+        content(
+            (String = "application/x-ndjson"),
+            (String = "text/csv"),
+        )
+    ),
+    responses(
+        (status = 200, description = "Outcome of every row", body = ImportReportDTO),
+        (status = 400, description = "`malformed_request`", body = ErrorDTO),
+        (status = 401, description = "`unauthorized`", body = ErrorDTO),
+        (
+            status = 403,
+            description = "`forbidden`, `cross_site_request` or `account_banned`",
+            body = ErrorDTO
+        ),
+        (status = 500, description = "`unexpected_error`", body = ErrorDTO),
+    )
+)]
+pub async fn import_users(
+    user_service: Data<dyn UserService>,
+    req: HttpRequest,
+    dto: Query<ImportUsersReqDTO>,
+    body: String,
+) -> Result<HttpResponse, ApiError> {
+    let dto = dto.into_inner();
+
+    let report = transfer::import_users(
+        user_service.as_ref(),
+        dto.format,
+        body.as_str(),
+        dto.conflict.into(),
+        dto.dry_run,
+        Locale::negotiate(&req),
+    )
+    .await;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users/export",
+    tag = "admin",
+    security(("session" = [])),
+    params(ExportUsersReqDTO),
+    responses(
+        (
+            status = 200,
+            description = "Every user as `UserRecordDTO` rows",
+            content(
+                (String = "application/x-ndjson"),
+                (String = "text/csv"),
+            )
+        ),
+        (status = 400, description = "`malformed_request`", body = ErrorDTO),
+        (status = 401, description = "`unauthorized`", body = ErrorDTO),
+        (status = 403, description = "`forbidden` or `account_banned`", body = ErrorDTO),
+        (status = 500, description = "`unexpected_error`", body = ErrorDTO),
+    )
+)]
+pub async fn export_users(
+    user_service: Data<dyn UserService>,
+    dto: Query<ExportUsersReqDTO>,
+) -> Result<HttpResponse, ApiError> {
+    let format = dto.into_inner().format;
+
+    let body = transfer::export_users(user_service.as_ref(), format).await?;
+
+    let disposition = ContentDisposition {
+        disposition: DispositionType::Attachment,
+        parameters: vec![DispositionParam::Filename(format!(
+            "users.{}",
+            format.extension()
+        ))],
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(disposition)
+        .body(body))
+}
+
 pub fn configure(cfg: &mut ServiceConfig) {
     cfg.service(
         scope("/users")
+            .wrap(Csrf::new("jwt"))
             .route("", get().to(get_users))
             .route("/profile", get().to(get_profile).wrap(AuthGuard::default()))
             .route("/registration", post().to(register_user))
             .route("/login", post().to(login_user))
             .route("/logout", post().to(logout_user).wrap(AuthGuard::default()))
             .route("/{login}", get().to(get_user)),
+    )
+    .service(
+        scope("/admin/users")
+            .wrap(AuthGuard::with_role(ADMIN_ROLE))
+            .wrap(Csrf::new("jwt"))
+            .app_data(PayloadConfig::new(IMPORT_MAX_BYTES))
+            .route("/import", post().to(import_users))
+            .route("/export", get().to(export_users)),
     );
 }
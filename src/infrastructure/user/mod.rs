@@ -1,4 +1,7 @@
+pub mod cache;
 pub mod controllers;
 pub mod models;
+pub mod persistence;
 pub mod repository;
 pub mod service;
+pub mod transfer;
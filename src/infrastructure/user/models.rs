@@ -1,60 +1,632 @@
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError, ValidationErrors};
 
-use crate::core::user::models::{User, Users};
+use crate::core::user::{
+    login::{check_login_length, normalize_login},
+    models::{
+        is_valid_role, ImportConflictPolicy, PasswordAlgorithm, SortOrder, User, UserLoginSearch,
+        UserQuery, UserRecord, UserSortField, Users,
+    },
+};
+use crate::infrastructure::{
+    constants::ENV_CONFIG,
+    errors::ApiError,
+    i18n::Locale,
+    models::FieldErrorDTO,
+    validation::{field_errors, login_error},
+};
 
-#[derive(Serialize)]
+lazy_static! {
+    static ref LOGIN_REGEX: Regex = Regex::new(r"^\S+$").unwrap();
+}
+
+// Length and policy apply to the login as it will be stored.
+fn validate_login_policy(login: &str) -> Result<(), ValidationError> {
+    ENV_CONFIG
+        .get_login_policy()
+        .check(normalize_login(login).as_str())
+        .map_err(login_error)
+}
+
+fn validate_login_length(login: &str) -> Result<(), ValidationError> {
+    check_login_length(normalize_login(login).as_str()).map_err(login_error)
+}
+
+fn validate_roles(roles: &[String]) -> Result<(), ValidationError> {
+    if roles.iter().all(|role| is_valid_role(role)) {
+        return Ok(());
+    }
+
+    let mut error = ValidationError::new("role_format");
+    error.message = Some("validation.role_format".into());
+    Err(error)
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct GetUserResDTO {
     id: i32,
     login: String,
+    created_at: DateTime<Utc>,
 }
 
-impl Into<GetUserResDTO> for User {
-    fn into(self) -> GetUserResDTO {
-        GetUserResDTO {
-            id: self.get_id(),
-            login: self.clone_login(),
+impl From<User> for GetUserResDTO {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.get_id(),
+            login: user.clone_login(),
+            created_at: user.get_created_at(),
         }
     }
 }
 
-impl Into<Vec<GetUserResDTO>> for Users {
-    fn into(self) -> Vec<GetUserResDTO> {
-        self.into_users()
+impl From<Users> for Vec<GetUserResDTO> {
+    fn from(users: Users) -> Self {
+        users
+            .into_users()
             .into_iter()
             .map(|item| item.into())
             .collect()
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSortFieldDTO {
+    #[default]
+    Id,
+    Login,
+    CreatedAt,
+}
+
+impl From<UserSortFieldDTO> for UserSortField {
+    fn from(dto: UserSortFieldDTO) -> Self {
+        match dto {
+            UserSortFieldDTO::Id => Self::Id,
+            UserSortFieldDTO::Login => Self::Login,
+            UserSortFieldDTO::CreatedAt => Self::CreatedAt,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrderDTO {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl From<SortOrderDTO> for SortOrder {
+    fn from(dto: SortOrderDTO) -> Self {
+        match dto {
+            SortOrderDTO::Asc => Self::Asc,
+            SortOrderDTO::Desc => Self::Desc,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchModeDTO {
+    Prefix,
+    #[default]
+    Substring,
+}
+
+fn default_page() -> usize {
+    1
+}
+
+fn default_per_page() -> usize {
+    20
+}
+
+#[derive(Serialize, Deserialize, Clone, Validate, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GetUsersReqDTO {
+    #[serde(default = "default_page")]
+    #[validate(range(min = 1, max = 1000000, message = "validation.page_range"))]
+    #[param(minimum = 1, maximum = 1000000, default = 1)]
+    pub page: usize,
+    #[serde(default = "default_per_page")]
+    #[validate(range(min = 1, max = 100, message = "validation.per_page_range"))]
+    #[param(minimum = 1, maximum = 100, default = 20)]
+    pub per_page: usize,
+    #[serde(default)]
+    #[param(inline)]
+    pub sort: UserSortFieldDTO,
+    #[serde(default)]
+    #[param(inline)]
+    pub order: SortOrderDTO,
+    /// Filters users by login.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[validate(length(max = 30, message = "validation.search_length"))]
+    #[param(max_length = 30)]
+    pub search: Option<String>,
+    #[serde(default)]
+    #[param(inline)]
+    pub search_mode: SearchModeDTO,
+}
+
+impl From<GetUsersReqDTO> for UserQuery {
+    fn from(dto: GetUsersReqDTO) -> Self {
+        let search = dto
+            .search
+            .filter(|search| !search.is_empty())
+            .map(|search| match dto.search_mode {
+                SearchModeDTO::Prefix => UserLoginSearch::Prefix(search),
+                SearchModeDTO::Substring => UserLoginSearch::Substring(search),
+            });
+
+        UserQuery::new(
+            search,
+            dto.sort.into(),
+            dto.order.into(),
+            (dto.page - 1) * dto.per_page,
+            dto.per_page,
+        )
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GetUsersResDTO {
+    items: Vec<GetUserResDTO>,
+    total: usize,
+    page: usize,
+    per_page: usize,
+    /// Path and query of the next page, `null` on the last one.
+    next: Option<String>,
+}
+
+impl GetUsersResDTO {
+    pub fn new(
+        items: Vec<GetUserResDTO>,
+        total: usize,
+        page: usize,
+        per_page: usize,
+        next: Option<String>,
+    ) -> Self {
+        Self {
+            items,
+            total,
+            page,
+            per_page,
+            next,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct GetProfileResDTO {
     id: i32,
     login: String,
+    /// Granted with `oped-admin grant-role`.
+    roles: Vec<String>,
 }
 
-impl Into<GetProfileResDTO> for User {
-    fn into(self) -> GetProfileResDTO {
-        GetProfileResDTO {
-            id: self.get_id(),
-            login: self.clone_login(),
+impl From<User> for GetProfileResDTO {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.get_id(),
+            login: user.clone_login(),
+            roles: user.clone_roles(),
         }
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate, ToSchema)]
 pub struct RegisterUserReqDTO {
-    pub login: String,
-    pub password: String,
+    /// Without whitespace; the characters allowed depend on `login.policy`.
+    #[validate(
+        required(message = "validation.login_required"),
+        regex(path = "LOGIN_REGEX", message = "validation.login_whitespace"),
+        custom = "validate_login_policy"
+    )]
+    #[schema(value_type = String, min_length = 3, max_length = 30, pattern = r"^\S+$")]
+    pub login: Option<String>,
+    #[validate(
+        required(message = "validation.password_required"),
+        length(min = 3, max = 30, message = "validation.password_length")
+    )]
+    #[schema(value_type = String, min_length = 3, max_length = 30, format = Password)]
+    pub password: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Validate, ToSchema)]
 pub struct LoginUserReqDTO {
-    pub login: String,
-    pub password: String,
+    #[validate(
+        required(message = "validation.login_required"),
+        custom = "validate_login_length"
+    )]
+    #[schema(value_type = String, min_length = 3, max_length = 30)]
+    pub login: Option<String>,
+    #[validate(
+        required(message = "validation.password_required"),
+        length(min = 3, max = 30, message = "validation.password_length")
+    )]
+    #[schema(value_type = String, min_length = 3, max_length = 30, format = Password)]
+    pub password: Option<String>,
 }
 
-#[derive(Serialize, Default)]
+#[derive(Serialize, Default, ToSchema)]
 pub struct LoginUserResDTO {}
 
-#[derive(Serialize, Default)]
+#[derive(Serialize, Default, ToSchema)]
 pub struct LogoutUserResDTO {}
+
+#[derive(Serialize, Deserialize, Clone, Copy, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PasswordAlgorithmDTO {
+    /// Hex SHA-256 of the password followed by `password_salt`; what this
+    /// service produces itself.
+    Sha256,
+    /// `$2a$`, `$2b$` or `$2y$` bcrypt hash; `password_salt` is ignored.
+    Bcrypt,
+}
+
+impl PasswordAlgorithmDTO {
+    pub fn matches(&self, hash: &str) -> bool {
+        match self {
+            Self::Sha256 => hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()),
+            Self::Bcrypt => {
+                hash.len() == 60
+                    && ["$2a$", "$2b$", "$2y$"]
+                        .iter()
+                        .any(|prefix| hash.starts_with(prefix))
+            }
+        }
+    }
+}
+
+impl From<PasswordAlgorithmDTO> for PasswordAlgorithm {
+    fn from(dto: PasswordAlgorithmDTO) -> Self {
+        match dto {
+            PasswordAlgorithmDTO::Sha256 => Self::Sha256,
+            PasswordAlgorithmDTO::Bcrypt => Self::Bcrypt,
+        }
+    }
+}
+
+impl From<PasswordAlgorithm> for PasswordAlgorithmDTO {
+    fn from(algorithm: PasswordAlgorithm) -> Self {
+        match algorithm {
+            PasswordAlgorithm::Sha256 => Self::Sha256,
+            PasswordAlgorithm::Bcrypt => Self::Bcrypt,
+        }
+    }
+}
+
+/// One user in an import or export file.
+#[derive(Serialize, Deserialize, Validate, ToSchema)]
+pub struct UserRecordDTO {
+    #[validate(
+        regex(path = "LOGIN_REGEX", message = "validation.login_whitespace"),
+        custom = "validate_login_policy"
+    )]
+    pub login: String,
+    pub password_algorithm: PasswordAlgorithmDTO,
+    pub password_hash: String,
+    #[serde(default)]
+    pub password_salt: String,
+    #[serde(default)]
+    #[validate(custom = "validate_roles")]
+    pub roles: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub banned: bool,
+}
+
+impl UserRecordDTO {
+    pub fn check(&self) -> Result<(), ValidationErrors> {
+        let mut errors = self.validate().err().unwrap_or_default();
+
+        if !self.password_algorithm.matches(self.password_hash.as_str()) {
+            let mut error = ValidationError::new("password_hash_format");
+            error.message = Some("validation.password_hash_format".into());
+            errors.add("password_hash", error);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl From<User> for UserRecordDTO {
+    fn from(user: User) -> Self {
+        Self {
+            login: user.clone_login(),
+            password_algorithm: user.get_password_algorithm().into(),
+            password_hash: user.clone_hash(),
+            password_salt: user.clone_salt(),
+            roles: user.clone_roles(),
+            created_at: user.get_created_at(),
+            banned: user.is_banned(),
+        }
+    }
+}
+
+impl From<UserRecordDTO> for UserRecord {
+    fn from(dto: UserRecordDTO) -> Self {
+        let hash = match dto.password_algorithm {
+            PasswordAlgorithmDTO::Sha256 => dto.password_hash.to_ascii_lowercase(),
+            PasswordAlgorithmDTO::Bcrypt => dto.password_hash,
+        };
+
+        UserRecord::new(
+            dto.login,
+            dto.password_algorithm.into(),
+            hash,
+            dto.password_salt,
+            dto.roles,
+            dto.created_at,
+            dto.banned,
+        )
+    }
+}
+
+// Roles are separated by spaces.
+#[derive(Serialize, Deserialize)]
+pub struct UserRecordCsvDTO {
+    login: String,
+    password_algorithm: PasswordAlgorithmDTO,
+    password_hash: String,
+    #[serde(default)]
+    password_salt: String,
+    #[serde(default)]
+    roles: String,
+    created_at: DateTime<Utc>,
+    #[serde(default)]
+    banned: bool,
+}
+
+impl From<UserRecordDTO> for UserRecordCsvDTO {
+    fn from(dto: UserRecordDTO) -> Self {
+        Self {
+            login: dto.login,
+            password_algorithm: dto.password_algorithm,
+            password_hash: dto.password_hash,
+            password_salt: dto.password_salt,
+            roles: dto.roles.join(" "),
+            created_at: dto.created_at,
+            banned: dto.banned,
+        }
+    }
+}
+
+impl From<UserRecordCsvDTO> for UserRecordDTO {
+    fn from(dto: UserRecordCsvDTO) -> Self {
+        Self {
+            login: dto.login,
+            password_algorithm: dto.password_algorithm,
+            password_hash: dto.password_hash,
+            password_salt: dto.password_salt,
+            roles: dto.roles.split_whitespace().map(str::to_owned).collect(),
+            created_at: dto.created_at,
+            banned: dto.banned,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferFormatDTO {
+    /// One `UserRecordDTO` JSON object per line.
+    #[default]
+    Jsonl,
+    /// A header row naming the `UserRecordDTO` fields, roles separated by
+    /// spaces.
+    Csv,
+}
+
+impl TransferFormatDTO {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Jsonl => "application/x-ndjson",
+            Self::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Jsonl => "jsonl",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportConflictDTO {
+    /// Keep the stored user and report the row as `skipped`.
+    Skip,
+    /// Replace the stored user, keeping its id.
+    Overwrite,
+    /// Import nothing if any login is taken.
+    #[default]
+    Fail,
+}
+
+impl From<ImportConflictDTO> for ImportConflictPolicy {
+    fn from(dto: ImportConflictDTO) -> Self {
+        match dto {
+            ImportConflictDTO::Skip => Self::Skip,
+            ImportConflictDTO::Overwrite => Self::Overwrite,
+            ImportConflictDTO::Fail => Self::Fail,
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportUsersReqDTO {
+    #[serde(default)]
+    #[param(inline)]
+    pub format: TransferFormatDTO,
+    #[serde(default)]
+    #[param(inline)]
+    pub conflict: ImportConflictDTO,
+    /// Only report what the import would do.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportUsersReqDTO {
+    #[serde(default)]
+    #[param(inline)]
+    pub format: TransferFormatDTO,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportOutcomeDTO {
+    Created,
+    Overwritten,
+    Skipped,
+    Failed,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImportRowDTO {
+    /// Line of the row in the uploaded file.
+    line: u64,
+    login: Option<String>,
+    outcome: ImportOutcomeDTO,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "login_already_used")]
+    code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    /// Field errors of a `validation_failed` row.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldErrorDTO>,
+}
+
+impl ImportRowDTO {
+    pub fn new(line: u64, login: Option<String>, outcome: ImportOutcomeDTO) -> Self {
+        Self {
+            line,
+            login,
+            outcome,
+            code: None,
+            message: None,
+            errors: vec![],
+        }
+    }
+
+    pub fn failed(line: u64, login: Option<String>, error: &ApiError, locale: Locale) -> Self {
+        let errors = match error {
+            ApiError::Validation(errors) => field_errors(errors, locale),
+            _ => vec![],
+        };
+
+        Self {
+            line,
+            login,
+            outcome: ImportOutcomeDTO::Failed,
+            code: Some(error.code().to_owned()),
+            message: Some(error.message(locale)),
+            errors,
+        }
+    }
+
+    pub fn get_line(&self) -> u64 {
+        self.line
+    }
+
+    pub fn get_outcome(&self) -> ImportOutcomeDTO {
+        self.outcome
+    }
+}
+
+impl Display for ImportRowDTO {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "line {}", self.line)?;
+
+        if let Some(login) = &self.login {
+            write!(f, " `{}`", login)?;
+        }
+
+        match (&self.code, &self.message) {
+            (Some(code), Some(message)) => write!(f, ": {}: {}", code, message)?,
+            _ => write!(f, ": {}", self.outcome.as_str())?,
+        }
+
+        for error in &self.errors {
+            write!(f, "; {}", error)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ImportOutcomeDTO {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Overwritten => "overwritten",
+            Self::Skipped => "skipped",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImportReportDTO {
+    dry_run: bool,
+    /// `false` after a dry run, or when a taken login stopped a `fail`
+    /// import; outcomes then say what would have happened.
+    applied: bool,
+    created: usize,
+    overwritten: usize,
+    skipped: usize,
+    failed: usize,
+    rows: Vec<ImportRowDTO>,
+}
+
+impl ImportReportDTO {
+    pub fn new(dry_run: bool, applied: bool, rows: Vec<ImportRowDTO>) -> Self {
+        let count = |outcome| rows.iter().filter(|row| row.outcome == outcome).count();
+
+        Self {
+            dry_run,
+            applied,
+            created: count(ImportOutcomeDTO::Created),
+            overwritten: count(ImportOutcomeDTO::Overwritten),
+            skipped: count(ImportOutcomeDTO::Skipped),
+            failed: count(ImportOutcomeDTO::Failed),
+            rows,
+        }
+    }
+
+    pub fn is_applied(&self) -> bool {
+        self.applied
+    }
+
+    pub fn get_failed(&self) -> usize {
+        self.failed
+    }
+
+    pub fn rows(&self) -> &[ImportRowDTO] {
+        &self.rows
+    }
+}
+
+impl Display for ImportReportDTO {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{} created, {} overwritten, {} skipped, {} failed",
+            self.created, self.overwritten, self.skipped, self.failed
+        )
+    }
+}
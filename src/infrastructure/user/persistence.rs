@@ -0,0 +1,335 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{create_dir_all, read_to_string, remove_file, rename, File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use tracing::warn;
+
+use crate::core::error::ErrorSource;
+use crate::core::user::models::User;
+
+use super::models::UserRecordDTO;
+
+const SNAPSHOT_FILE: &str = "users.json";
+const LOG_FILE: &str = "users.log";
+const LOCK_FILE: &str = "users.lock";
+
+#[derive(Serialize, Deserialize)]
+struct StoredUserDTO {
+    id: i32,
+    #[serde(flatten)]
+    record: UserRecordDTO,
+}
+
+impl From<&User> for StoredUserDTO {
+    fn from(user: &User) -> Self {
+        Self {
+            id: user.get_id(),
+            record: user.clone().into(),
+        }
+    }
+}
+
+impl From<StoredUserDTO> for User {
+    fn from(dto: StoredUserDTO) -> Self {
+        User::from_record(dto.id, dto.record.into())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotDTO {
+    next_id: i32,
+    users: Vec<StoredUserDTO>,
+}
+
+/// One line of `users.log`. Every entry carries the whole user, so replaying
+/// an entry that is already part of the snapshot changes nothing.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum LogEntryDTO {
+    Insert(StoredUserDTO),
+    Update(StoredUserDTO),
+}
+
+pub struct RecoveredUsers {
+    pub users: Vec<User>,
+    pub next_id: i32,
+}
+
+struct UserLog {
+    file: File,
+    len: u64,
+}
+
+/// Persistence of `MemoryUserRepository` in a directory:
+///
+/// - `users.json`, a snapshot replaced atomically (written to a temporary
+///   file, synced, then renamed over the old one);
+/// - `users.log`, every write since that snapshot, one JSON object per line,
+///   synced before the write is acknowledged;
+/// - `users.lock`, held for the lifetime of the store so the server and
+///   `oped-admin` never write the same directory at once.
+pub struct UserStore {
+    dir: PathBuf,
+    log: Mutex<UserLog>,
+    _lock: File,
+}
+
+fn describe(path: &Path, error: impl ToString) -> String {
+    format!("{}: {}", path.display(), error.to_string())
+}
+
+// Makes a rename inside `dir` survive a crash.
+fn sync_dir(dir: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+
+    #[cfg(not(unix))]
+    let _ = dir;
+
+    Ok(())
+}
+
+fn upsert(users: &mut Vec<User>, user: User) {
+    match users.binary_search_by_key(&user.get_id(), User::get_id) {
+        Ok(index) => users[index] = user,
+        Err(index) => users.insert(index, user),
+    }
+}
+
+impl UserStore {
+    pub fn open(dir: &Path) -> Result<(Self, RecoveredUsers), String> {
+        create_dir_all(dir).map_err(|error| describe(dir, error))?;
+
+        let lock_path = dir.join(LOCK_FILE);
+
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(|error| describe(&lock_path, error))?;
+
+        lock.try_lock().map_err(|_| {
+            describe(
+                &lock_path,
+                "used by another process; stop the server before running oped-admin",
+            )
+        })?;
+
+        let mut recovered = Self::read_snapshot(dir)?;
+
+        let log_path = dir.join(LOG_FILE);
+
+        let len = Self::replay_log(&log_path, &mut recovered)?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .map_err(|error| describe(&log_path, error))?;
+
+        // Cuts off a torn last line so new entries start on a line of their own.
+        file.set_len(len)
+            .map_err(|error| describe(&log_path, error))?;
+
+        let store = Self {
+            dir: dir.to_owned(),
+            log: Mutex::new(UserLog { file, len }),
+            _lock: lock,
+        };
+
+        Ok((store, recovered))
+    }
+
+    fn read_snapshot(dir: &Path) -> Result<RecoveredUsers, String> {
+        let path = dir.join(SNAPSHOT_FILE);
+
+        if !path.exists() {
+            return Ok(RecoveredUsers {
+                users: vec![],
+                next_id: 1,
+            });
+        }
+
+        let content = read_to_string(&path).map_err(|error| describe(&path, error))?;
+
+        let snapshot: SnapshotDTO =
+            serde_json::from_str(&content).map_err(|error| describe(&path, error))?;
+
+        let mut users: Vec<User> = snapshot.users.into_iter().map(User::from).collect();
+
+        users.sort_by_key(User::get_id);
+
+        Ok(RecoveredUsers {
+            users,
+            next_id: snapshot.next_id,
+        })
+    }
+
+    /// Applies every complete entry and returns the length of the log they
+    /// span. Only the last line may be broken: that is a write the crash
+    /// interrupted, which was never acknowledged.
+    fn replay_log(path: &Path, recovered: &mut RecoveredUsers) -> Result<u64, String> {
+        if !path.exists() {
+            return Ok(0);
+        }
+
+        let content = read_to_string(path).map_err(|error| describe(path, error))?;
+
+        let mut len = 0;
+
+        let mut lines = content.split_inclusive('\n').enumerate().peekable();
+
+        while let Some((index, line)) = lines.next() {
+            let is_last = lines.peek().is_none();
+
+            let is_complete = line.ends_with('\n');
+
+            let entry = match serde_json::from_str::<LogEntryDTO>(line) {
+                Ok(entry) if is_complete => entry,
+                Err(error) if !is_last => {
+                    return Err(describe(path, format!("line {}: {}", index + 1, error)));
+                }
+                _ => {
+                    warn!(
+                        line = index + 1,
+                        "ignoring incomplete last entry of the user log"
+                    );
+                    break;
+                }
+            };
+
+            let (LogEntryDTO::Insert(stored) | LogEntryDTO::Update(stored)) = entry;
+
+            recovered.next_id = recovered.next_id.max(stored.id.saturating_add(1));
+
+            upsert(&mut recovered.users, stored.into());
+
+            len += line.len() as u64;
+        }
+
+        Ok(len)
+    }
+
+    fn lock_log(&self) -> MutexGuard<'_, UserLog> {
+        match self.log.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn append(&self, entry: LogEntryDTO) -> Result<(), ErrorSource> {
+        let mut line = serde_json::to_string(&entry)
+            .map_err(|error| ErrorSource::new(format!("user log entry: {}", error)))?;
+
+        line.push('\n');
+
+        let mut log = self.lock_log();
+
+        let written = log
+            .file
+            .write_all(line.as_bytes())
+            .and_then(|_| log.file.sync_data());
+
+        if let Err(error) = written {
+            // Drop whatever part made it to disk; recovery would ignore it
+            // anyway, but the next entry must start on a fresh line.
+            let len = log.len;
+            log.file.set_len(len).ok();
+
+            return Err(ErrorSource::new(describe(&self.dir.join(LOG_FILE), error)));
+        }
+
+        log.len += line.len() as u64;
+
+        Ok(())
+    }
+
+    pub fn append_insert(&self, user: &User) -> Result<(), ErrorSource> {
+        self.append(LogEntryDTO::Insert(user.into()))
+    }
+
+    pub fn append_update(&self, user: &User) -> Result<(), ErrorSource> {
+        self.append(LogEntryDTO::Update(user.into()))
+    }
+
+    /// Bytes logged since the last snapshot; read it under the same lock as
+    /// the users passed to `snapshot`.
+    pub fn log_len(&self) -> u64 {
+        self.lock_log().len
+    }
+
+    // Drops the first `log_len` bytes of the log, which `users` now cover.
+    pub fn snapshot(&self, users: &[User], next_id: i32, log_len: u64) -> Result<(), ErrorSource> {
+        let path = self.dir.join(SNAPSHOT_FILE);
+        let temp_path = self.dir.join(format!("{}.tmp", SNAPSHOT_FILE));
+
+        let snapshot = SnapshotDTO {
+            next_id,
+            users: users.iter().map(StoredUserDTO::from).collect(),
+        };
+
+        let content = serde_json::to_vec(&snapshot)
+            .map_err(|error| ErrorSource::new(describe(&path, error)))?;
+
+        let written = File::create(&temp_path)
+            .and_then(|mut file| {
+                file.write_all(&content)?;
+                file.sync_all()
+            })
+            .and_then(|_| rename(&temp_path, &path))
+            .and_then(|_| sync_dir(&self.dir));
+
+        if let Err(error) = written {
+            remove_file(&temp_path).ok();
+
+            return Err(ErrorSource::new(describe(&path, error)));
+        }
+
+        self.compact_log(log_len)
+    }
+
+    fn compact_log(&self, covered: u64) -> Result<(), ErrorSource> {
+        let path = self.dir.join(LOG_FILE);
+        let temp_path = self.dir.join(format!("{}.tmp", LOG_FILE));
+
+        let mut log = self.lock_log();
+
+        let result = (|| {
+            let mut tail = vec![];
+
+            if log.len > covered {
+                let mut reader = File::open(&path)?;
+                reader.seek(SeekFrom::Start(covered))?;
+                std::io::Read::read_to_end(&mut reader, &mut tail)?;
+            }
+
+            let mut file = File::create(&temp_path)?;
+            file.write_all(&tail)?;
+            file.sync_all()?;
+
+            rename(&temp_path, &path)?;
+            sync_dir(&self.dir)?;
+
+            let file = OpenOptions::new().append(true).open(&path)?;
+
+            Ok::<_, std::io::Error>((file, tail.len() as u64))
+        })();
+
+        match result {
+            Ok((file, len)) => {
+                *log = UserLog { file, len };
+
+                Ok(())
+            }
+            Err(error) => {
+                remove_file(&temp_path).ok();
+
+                // The old log is still complete; replaying entries the
+                // snapshot already has is harmless.
+                Err(ErrorSource::new(describe(&path, error)))
+            }
+        }
+    }
+}
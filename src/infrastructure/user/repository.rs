@@ -1,68 +1,254 @@
+use actix_web::rt::time::sleep;
 use async_trait::async_trait;
-use std::sync::{Arc, Mutex, MutexGuard};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+use tracing::{instrument, warn};
 
+use crate::core::error::ErrorSource;
 use crate::core::user::{
-    models::User,
+    login::login_key,
+    models::{SortOrder, User, UserQuery, UserRecord, UserSortField, Users, UsersPage},
     repository::{
-        UserRepository, UserRepositoryInsertError, UserRepositorySelectAllError,
-        UserRepositorySelectOneError,
+        UserRepository, UserRepositoryFlushError, UserRepositoryHealthCheckError,
+        UserRepositoryInsertError, UserRepositorySelectManyError, UserRepositorySelectOneError,
+        UserRepositoryUpdateError,
     },
 };
+use crate::infrastructure::config::{EnvConfig, StorageBackend};
 
+use super::cache::CachedUserRepository;
+use super::persistence::UserStore;
+
+pub fn build_user_repository(config: &EnvConfig) -> Result<Arc<dyn UserRepository>, String> {
+    let user_repository: Arc<dyn UserRepository> = match config.get_storage_backend() {
+        StorageBackend::Memory => {
+            let data_dir = config.clone_storage_data_dir();
+
+            if data_dir.is_empty() {
+                Arc::new(MemoryUserRepository::new())
+            } else {
+                Arc::new(MemoryUserRepository::open(Path::new(&data_dir))?)
+            }
+        }
+    };
+
+    match config.get_storage_cache() {
+        Some((ttl, capacity)) => Ok(Arc::new(CachedUserRepository::new(
+            user_repository,
+            ttl,
+            capacity,
+        ))),
+        None => Ok(user_repository),
+    }
+}
+
+pub async fn flush_periodically(user_repository: Arc<dyn UserRepository>, interval: Duration) {
+    loop {
+        sleep(interval).await;
+
+        if let Err(UserRepositoryFlushError::UnexpectedError(source)) =
+            user_repository.flush().await
+        {
+            warn!(error = %source, "failed to flush user storage");
+        }
+    }
+}
+
+#[derive(Default)]
+struct UserTable {
+    by_id: HashMap<i32, User>,
+    by_login: HashMap<String, i32>,
+}
+
+impl UserTable {
+    fn from_users(users: Vec<User>) -> Self {
+        let mut table = Self::default();
+
+        for user in users {
+            table.put(login_key(user.clone_login().as_str()), user);
+        }
+
+        table
+    }
+
+    fn put(&mut self, key: String, user: User) {
+        self.by_login.insert(key, user.get_id());
+        self.by_id.insert(user.get_id(), user);
+    }
+}
+
+/// Users indexed by id and by login key. Ids are handed out in insertion
+/// order and never reused.
+///
+/// Writes are serialized by `writer`, taken before `table`: a write checks
+/// the table under the read lock, appends to the log, and only then takes the
+/// write lock to apply the change. Lookups therefore never wait for an fsync,
+/// and a write the log rejects leaves the table untouched.
 pub struct MemoryUserRepository {
-    shared_users: Arc<Mutex<Vec<User>>>,
-    shared_index: Arc<Mutex<i32>>,
+    writer: Mutex<()>,
+    table: RwLock<UserTable>,
+    next_id: AtomicI32,
+    store: Option<UserStore>,
+}
+
+impl Default for MemoryUserRepository {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MemoryUserRepository {
-    pub fn new(shared_users: Arc<Mutex<Vec<User>>>, shared_index: Arc<Mutex<i32>>) -> Self {
+    pub fn new() -> Self {
         Self {
-            shared_users,
-            shared_index,
+            writer: Mutex::new(()),
+            table: RwLock::new(UserTable::default()),
+            next_id: AtomicI32::new(1),
+            store: None,
+        }
+    }
+
+    pub fn open(data_dir: &Path) -> Result<Self, String> {
+        let (store, recovered) = UserStore::open(data_dir)?;
+
+        Ok(Self {
+            writer: Mutex::new(()),
+            table: RwLock::new(UserTable::from_users(recovered.users)),
+            next_id: AtomicI32::new(recovered.next_id),
+            store: Some(store),
+        })
+    }
+
+    fn lock_writer(&self) -> MutexGuard<'_, ()> {
+        match self.writer.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
         }
     }
 
-    fn lock_users(&self) -> MutexGuard<Vec<User>> {
-        match self.shared_users.lock() {
+    fn read_table(&self) -> RwLockReadGuard<'_, UserTable> {
+        match self.table.read() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         }
     }
 
-    fn lock_index(&self) -> MutexGuard<i32> {
-        match self.shared_index.lock() {
+    fn write_table(&self) -> RwLockWriteGuard<'_, UserTable> {
+        match self.table.write() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         }
     }
+
+    fn push<F>(&self, login: String, build: F) -> Result<i32, UserRepositoryInsertError>
+    where
+        F: FnOnce(i32) -> User,
+    {
+        let key = login_key(login.as_str());
+
+        let _writer = self.lock_writer();
+
+        if self.read_table().by_login.contains_key(&key) {
+            return Err(UserRepositoryInsertError::LoginAlreadyUsed);
+        }
+
+        let user_id = self.next_id.load(Ordering::Acquire);
+
+        let next_id = user_id.checked_add(1).ok_or_else(|| {
+            UserRepositoryInsertError::UnexpectedError(ErrorSource::new(
+                "user id space is exhausted",
+            ))
+        })?;
+
+        let user = build(user_id);
+
+        if let Some(store) = &self.store {
+            store
+                .append_insert(&user)
+                .map_err(UserRepositoryInsertError::UnexpectedError)?;
+        }
+
+        self.write_table().put(key, user);
+
+        self.next_id.store(next_id, Ordering::Release);
+
+        Ok(user_id)
+    }
 }
 
 #[async_trait]
 impl UserRepository for MemoryUserRepository {
-    async fn select_all(&self) -> Result<Vec<User>, UserRepositorySelectAllError> {
-        let users = self.lock_users();
+    #[instrument(name = "user_repository.select_many", skip_all)]
+    async fn select_many(
+        &self,
+        query: UserQuery,
+    ) -> Result<UsersPage, UserRepositorySelectManyError> {
+        let table = self.read_table();
 
-        Ok((*users).clone())
-    }
+        let mut found: Vec<&User> = table
+            .by_id
+            .values()
+            .filter(|user| match query.get_search() {
+                Some(search) => search.matches(user.clone_login().as_str()),
+                None => true,
+            })
+            .collect();
 
-    async fn select_one_by_id(&self, id: i32) -> Result<User, UserRepositorySelectOneError> {
-        let users = self.lock_users();
+        found.sort_by(|a, b| {
+            let ordering = match query.get_sort_field() {
+                UserSortField::Id => a.get_id().cmp(&b.get_id()),
+                UserSortField::Login => a
+                    .clone_login()
+                    .cmp(&b.clone_login())
+                    .then(a.get_id().cmp(&b.get_id())),
+                UserSortField::CreatedAt => a
+                    .get_created_at()
+                    .cmp(&b.get_created_at())
+                    .then(a.get_id().cmp(&b.get_id())),
+            };
 
-        let user = (*users).iter().find(|user| user.get_id() == id);
+            match query.get_sort_order() {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            }
+        });
 
-        match user {
+        let total = found.len();
+
+        let page = found
+            .into_iter()
+            .skip(query.get_offset())
+            .take(query.get_limit())
+            .cloned()
+            .collect();
+
+        Ok(UsersPage::new(Users::new(page), total))
+    }
+
+    #[instrument(name = "user_repository.select_one_by_id", skip(self))]
+    async fn select_one_by_id(&self, id: i32) -> Result<User, UserRepositorySelectOneError> {
+        match self.read_table().by_id.get(&id) {
             Some(user) => Ok(user.clone()),
             None => Err(UserRepositorySelectOneError::NotFound),
         }
     }
 
+    #[instrument(name = "user_repository.select_one_by_login", skip_all)]
     async fn select_one_by_login(
         &self,
         login: String,
     ) -> Result<User, UserRepositorySelectOneError> {
-        let users = self.lock_users();
+        let key = login_key(login.as_str());
+
+        let table = self.read_table();
 
-        let user = (*users).iter().find(|user| user.clone_login() == login);
+        let user = table
+            .by_login
+            .get(&key)
+            .and_then(|user_id| table.by_id.get(user_id));
 
         match user {
             Some(user) => Ok(user.clone()),
@@ -70,32 +256,101 @@ impl UserRepository for MemoryUserRepository {
         }
     }
 
+    #[instrument(name = "user_repository.insert", skip_all)]
     async fn insert(
         &self,
         login: String,
         hash: String,
         salt: String,
     ) -> Result<i32, UserRepositoryInsertError> {
-        let mut users = self.lock_users();
+        self.push(login.clone(), |user_id| {
+            User::new(user_id, login, hash, salt, Utc::now())
+        })
+    }
 
-        if (*users)
-            .iter()
-            .find(|user| user.clone_login() == login)
-            .is_some()
-        {
-            return Err(UserRepositoryInsertError::LoginAlreadyUsed);
+    #[instrument(name = "user_repository.insert_record", skip_all)]
+    async fn insert_record(&self, record: UserRecord) -> Result<i32, UserRepositoryInsertError> {
+        self.push(record.clone_login(), |user_id| {
+            User::from_record(user_id, record)
+        })
+    }
+
+    #[instrument(name = "user_repository.update", skip_all)]
+    async fn update(&self, user: User) -> Result<(), UserRepositoryUpdateError> {
+        let key = login_key(user.clone_login().as_str());
+
+        let _writer = self.lock_writer();
+
+        let stored_key = {
+            let table = self.read_table();
+
+            let Some(stored) = table.by_id.get(&user.get_id()) else {
+                return Err(UserRepositoryUpdateError::NotFound);
+            };
+
+            let stored_key = login_key(stored.clone_login().as_str());
+
+            if key != stored_key && table.by_login.contains_key(&key) {
+                return Err(UserRepositoryUpdateError::UnexpectedError(
+                    ErrorSource::new("login is already used by another user"),
+                ));
+            }
+
+            stored_key
+        };
+
+        if let Some(store) = &self.store {
+            store
+                .append_update(&user)
+                .map_err(UserRepositoryUpdateError::UnexpectedError)?;
+        }
+
+        let mut table = self.write_table();
+
+        if key != stored_key {
+            table.by_login.remove(&stored_key);
         }
 
-        let mut index = self.lock_index();
+        table.put(key, user);
 
-        let user_id = *index;
+        Ok(())
+    }
 
-        let user = User::new(user_id, login, hash, salt);
+    #[instrument(name = "user_repository.health_check", skip_all)]
+    async fn health_check(&self) -> Result<(), UserRepositoryHealthCheckError> {
+        // The lock recovers from poisoning, so reaching it is enough.
+        drop(self.read_table());
 
-        (*users).push(user);
+        Ok(())
+    }
 
-        *index += 1;
+    #[instrument(name = "user_repository.flush", skip_all)]
+    async fn flush(&self) -> Result<(), UserRepositoryFlushError> {
+        let Some(store) = &self.store else {
+            // Nothing outlives the process, so there is nothing to write out.
+            return Ok(());
+        };
 
-        Ok(user_id)
+        let (mut users, next_id, log_len) = {
+            // Every logged write has reached the table while no writer runs.
+            let _writer = self.lock_writer();
+            let table = self.read_table();
+
+            (
+                table.by_id.values().cloned().collect::<Vec<User>>(),
+                self.next_id.load(Ordering::Acquire),
+                store.log_len(),
+            )
+        };
+
+        users.sort_by_key(User::get_id);
+
+        if log_len == 0 {
+            return Ok(());
+        }
+
+        store
+            .snapshot(&users, next_id, log_len)
+            .map_err(UserRepositoryFlushError::UnexpectedError)
     }
 }
@@ -1,38 +1,194 @@
 use async_trait::async_trait;
-use sha256::digest;
+use std::collections::HashSet;
 use std::sync::Arc;
+use tracing::instrument;
 
 use crate::core::user::{
-    models::{User, Users},
-    repository::UserRepository,
+    login::{login_key, normalize_login, LoginPolicy},
+    models::{ImportConflictPolicy, PasswordAlgorithm, User, UserQuery, UserRecord, UsersPage},
+    repository::{UserRepository, UserRepositorySelectOneError},
     service::{
-        UserService, UserServiceGetAllError, UserServiceGetOneError, UserServiceLoginError,
-        UserServiceRegisterError,
+        ImportOutcome, ImportResult, UserService, UserServiceGetManyError, UserServiceGetOneError,
+        UserServiceImportError, UserServiceLoginError, UserServiceRegisterError,
+        UserServiceUpdateError,
     },
 };
-use crate::infrastructure::{models::JwtData, utils::generate_salt};
+use crate::infrastructure::{
+    metrics::{observe_login, observe_registration},
+    models::JwtData,
+    utils::{generate_salt, hash_password, verify_password},
+};
 
 pub struct UserServiceImp {
     user_repository: Arc<dyn UserRepository>,
+    login_policy: LoginPolicy,
 }
 
 impl UserServiceImp {
-    pub fn new(user_repository: Arc<dyn UserRepository>) -> Self {
-        Self { user_repository }
+    pub fn new(user_repository: Arc<dyn UserRepository>, login_policy: LoginPolicy) -> Self {
+        Self {
+            user_repository,
+            login_policy,
+        }
+    }
+
+    async fn create_user(
+        &self,
+        login: String,
+        password: String,
+    ) -> Result<User, UserServiceRegisterError> {
+        let login = normalize_login(login.as_str());
+
+        self.login_policy.check(login.as_str())?;
+
+        let salt = generate_salt(64);
+
+        let hash = hash_password(password.as_str(), salt.as_str());
+
+        let result = self.user_repository.insert(login, hash, salt).await;
+
+        if let Err(error) = result {
+            return Err(error.into());
+        }
+
+        let user_id = result.unwrap();
+
+        let result = self.user_repository.select_one_by_id(user_id).await;
+
+        match result {
+            Ok(user) => Ok(user),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn check_credentials(
+        &self,
+        login: String,
+        password: String,
+    ) -> Result<User, UserServiceLoginError> {
+        let user = self
+            .user_repository
+            .select_one_by_login(normalize_login(login.as_str()))
+            .await;
+
+        if let Err(error) = user {
+            return Err(error.into());
+        }
+
+        let user = user.unwrap();
+
+        if !verify_password(
+            user.get_password_algorithm(),
+            password.as_str(),
+            user.clone_hash().as_str(),
+            user.clone_salt().as_str(),
+        ) {
+            return Err(UserServiceLoginError::WrongPassword);
+        }
+
+        // Checked after the password so a ban is only revealed to the owner.
+        if user.is_banned() {
+            return Err(UserServiceLoginError::Banned);
+        }
+
+        Ok(user)
+    }
+
+    async fn update_user<F>(&self, login: String, change: F) -> Result<User, UserServiceUpdateError>
+    where
+        F: FnOnce(&mut User) + Send,
+    {
+        let mut user = self
+            .user_repository
+            .select_one_by_login(normalize_login(login.as_str()))
+            .await?;
+
+        change(&mut user);
+
+        self.user_repository.update(user.clone()).await?;
+
+        Ok(user)
+    }
+
+    async fn plan_import(
+        &self,
+        record: &mut UserRecord,
+        conflict: ImportConflictPolicy,
+        seen: &mut HashSet<String>,
+    ) -> Result<ImportStep, UserServiceImportError> {
+        let login = normalize_login(record.clone_login().as_str());
+
+        self.login_policy.check(login.as_str())?;
+
+        if !seen.insert(login_key(login.as_str())) {
+            return Err(UserServiceImportError::LoginRepeated);
+        }
+
+        record.set_login(login.clone());
+
+        match self.user_repository.select_one_by_login(login).await {
+            Ok(stored) => match conflict {
+                ImportConflictPolicy::Skip => Ok(ImportStep::Skip),
+                ImportConflictPolicy::Overwrite => Ok(ImportStep::Overwrite(stored.get_id())),
+                ImportConflictPolicy::Fail => Err(UserServiceImportError::LoginAlreadyUsed),
+            },
+            Err(UserRepositorySelectOneError::NotFound) => Ok(ImportStep::Create),
+            Err(UserRepositorySelectOneError::UnexpectedError(source)) => {
+                Err(UserServiceImportError::UnexpectedError(source))
+            }
+        }
+    }
+
+    async fn apply_import(&self, record: UserRecord, step: ImportStep) -> ImportOutcome {
+        let result = match step {
+            ImportStep::Create => self
+                .user_repository
+                .insert_record(record)
+                .await
+                .map(|_| ImportOutcome::Created)
+                .map_err(UserServiceImportError::from),
+            ImportStep::Overwrite(id) => self
+                .user_repository
+                .update(User::from_record(id, record))
+                .await
+                .map(|_| ImportOutcome::Overwritten)
+                .map_err(UserServiceImportError::from),
+            ImportStep::Skip => Ok(ImportOutcome::Skipped),
+        };
+
+        result.unwrap_or_else(ImportOutcome::Failed)
+    }
+}
+
+enum ImportStep {
+    Create,
+    Overwrite(i32),
+    Skip,
+}
+
+impl From<&ImportStep> for ImportOutcome {
+    fn from(step: &ImportStep) -> Self {
+        match step {
+            ImportStep::Create => Self::Created,
+            ImportStep::Overwrite(_) => Self::Overwritten,
+            ImportStep::Skip => Self::Skipped,
+        }
     }
 }
 
 #[async_trait]
 impl UserService for UserServiceImp {
-    async fn get_all(&self) -> Result<Users, UserServiceGetAllError> {
-        let result = self.user_repository.select_all().await;
+    #[instrument(name = "user_service.get_many", skip_all)]
+    async fn get_many(&self, query: UserQuery) -> Result<UsersPage, UserServiceGetManyError> {
+        let result = self.user_repository.select_many(query).await;
 
         match result {
-            Ok(users) => Ok(Users::new(users)),
+            Ok(page) => Ok(page),
             Err(error) => Err(error.into()),
         }
     }
 
+    #[instrument(name = "user_service.get_one_by_id", skip(self))]
     async fn get_one_by_id(&self, id: i32) -> Result<User, UserServiceGetOneError> {
         let result = self.user_repository.select_one_by_id(id).await;
 
@@ -42,8 +198,12 @@ impl UserService for UserServiceImp {
         }
     }
 
+    #[instrument(name = "user_service.get_one_by_login", skip_all)]
     async fn get_one_by_login(&self, login: String) -> Result<User, UserServiceGetOneError> {
-        let result = self.user_repository.select_one_by_login(login).await;
+        let result = self
+            .user_repository
+            .select_one_by_login(normalize_login(login.as_str()))
+            .await;
 
         match result {
             Ok(user) => Ok(user),
@@ -51,60 +211,127 @@ impl UserService for UserServiceImp {
         }
     }
 
+    #[instrument(name = "user_service.register", skip_all)]
     async fn register(
         &self,
         login: String,
-        mut password: String,
+        password: String,
     ) -> Result<User, UserServiceRegisterError> {
-        let salt = generate_salt(64);
+        let result = self.create_user(login, password).await;
 
-        password.push_str(salt.as_str());
+        observe_registration(&result);
 
-        let hash = digest(password);
+        result
+    }
 
-        let result = self.user_repository.insert(login, hash, salt).await;
+    #[instrument(name = "user_service.login", skip_all)]
+    async fn login(
+        &self,
+        login: String,
+        password: String,
+    ) -> Result<String, UserServiceLoginError> {
+        let result = self.check_credentials(login, password).await;
 
-        if let Err(error) = result {
-            return Err(error.into());
-        }
+        observe_login(&result);
 
-        let user_id = result.unwrap();
+        let user = result?;
 
-        let result = self.user_repository.select_one_by_id(user_id).await;
+        let jwt_data = JwtData::new(user.get_id());
 
-        match result {
-            Ok(user) => Ok(user),
-            Err(error) => Err(error.into()),
-        }
+        let token = jwt_data.into_token();
+
+        Ok(token)
     }
 
-    async fn login(
+    #[instrument(name = "user_service.reset_password", skip_all)]
+    async fn reset_password(
         &self,
         login: String,
-        mut password: String,
-    ) -> Result<String, UserServiceLoginError> {
-        let user = self.user_repository.select_one_by_login(login).await;
+        password: String,
+    ) -> Result<User, UserServiceUpdateError> {
+        let salt = generate_salt(64);
 
-        if let Err(error) = user {
-            return Err(error.into());
-        }
+        let hash = hash_password(password.as_str(), salt.as_str());
 
-        let user = user.unwrap();
+        self.update_user(login, |user| {
+            user.set_password(PasswordAlgorithm::Sha256, hash, salt)
+        })
+        .await
+    }
 
-        let salt = user.clone_salt();
+    #[instrument(name = "user_service.grant_role", skip(self, login))]
+    async fn grant_role(
+        &self,
+        login: String,
+        role: String,
+    ) -> Result<User, UserServiceUpdateError> {
+        self.update_user(login, |user| user.grant_role(role)).await
+    }
 
-        password.push_str(salt.as_str());
+    #[instrument(name = "user_service.revoke_role", skip(self, login))]
+    async fn revoke_role(
+        &self,
+        login: String,
+        role: String,
+    ) -> Result<User, UserServiceUpdateError> {
+        self.update_user(login, |user| user.revoke_role(role.as_str()))
+            .await
+    }
 
-        let hash = digest(password);
+    #[instrument(name = "user_service.set_banned", skip(self, login))]
+    async fn set_banned(
+        &self,
+        login: String,
+        banned: bool,
+    ) -> Result<User, UserServiceUpdateError> {
+        self.update_user(login, |user| user.set_banned(banned))
+            .await
+    }
 
-        if hash != user.clone_hash() {
-            return Err(UserServiceLoginError::WrongPassword);
+    #[instrument(name = "user_service.import", skip(self, records), fields(records = records.len()))]
+    async fn import(
+        &self,
+        records: Vec<UserRecord>,
+        conflict: ImportConflictPolicy,
+        dry_run: bool,
+    ) -> ImportResult {
+        let mut seen = HashSet::new();
+
+        let mut plan = Vec::with_capacity(records.len());
+
+        for mut record in records {
+            let step = self.plan_import(&mut record, conflict, &mut seen).await;
+
+            plan.push((record, step));
         }
 
-        let jwt_data = JwtData::new(user.get_id());
+        let conflicted = plan
+            .iter()
+            .any(|(_, step)| matches!(step, Err(UserServiceImportError::LoginAlreadyUsed)));
 
-        let token = jwt_data.into_token();
+        if dry_run || conflicted {
+            let outcomes = plan
+                .iter()
+                .map(|(_, step)| match step {
+                    Ok(step) => step.into(),
+                    Err(error) => ImportOutcome::Failed(error.clone()),
+                })
+                .collect();
 
-        Ok(token)
+            return ImportResult::new(outcomes, false);
+        }
+
+        let mut outcomes = Vec::with_capacity(plan.len());
+
+        for (record, step) in plan {
+            let outcome = match step {
+                Ok(step) => self.apply_import(record, step).await,
+                Err(error) => ImportOutcome::Failed(error),
+            };
+
+            outcomes.push(outcome);
+        }
+
+        ImportResult::new(outcomes, true)
     }
 }
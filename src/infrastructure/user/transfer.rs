@@ -0,0 +1,171 @@
+use csv::{ReaderBuilder, Trim, WriterBuilder};
+
+use crate::core::user::{
+    models::{ImportConflictPolicy, SortOrder, UserQuery, UserSortField},
+    service::{ImportOutcome, UserService, UserServiceGetManyError},
+};
+use crate::infrastructure::{errors::ApiError, i18n::Locale};
+
+use super::models::{
+    ImportOutcomeDTO, ImportReportDTO, ImportRowDTO, TransferFormatDTO, UserRecordCsvDTO,
+    UserRecordDTO,
+};
+
+// A valid record, or the login (when it could be read) and why the row failed.
+type Row = (u64, Result<UserRecordDTO, (Option<String>, ApiError)>);
+
+fn read_records(format: TransferFormatDTO, input: &str) -> Vec<Row> {
+    let rows: Vec<(u64, Result<UserRecordDTO, String>)> = match format {
+        TransferFormatDTO::Jsonl => input
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                let record = serde_json::from_str(line).map_err(|error| error.to_string());
+
+                (index as u64 + 1, record)
+            })
+            .collect(),
+        TransferFormatDTO::Csv => {
+            let mut reader = ReaderBuilder::new()
+                .trim(Trim::All)
+                .from_reader(input.as_bytes());
+
+            let headers = match reader.headers() {
+                Ok(headers) => headers.clone(),
+                Err(error) => {
+                    return vec![(
+                        1,
+                        Err((None, ApiError::MalformedRequest(error.to_string()))),
+                    )]
+                }
+            };
+
+            reader
+                .records()
+                .map(|row| {
+                    let position = match &row {
+                        Ok(row) => row.position(),
+                        Err(error) => error.position(),
+                    }
+                    .map(|position| position.line())
+                    .unwrap_or_default();
+
+                    let record = row
+                        .and_then(|row| row.deserialize::<UserRecordCsvDTO>(Some(&headers)))
+                        .map(UserRecordDTO::from)
+                        .map_err(|error| error.to_string());
+
+                    (position, record)
+                })
+                .collect()
+        }
+    };
+
+    rows.into_iter()
+        .map(|(line, record)| {
+            let record = record
+                .map_err(|details| (None, ApiError::MalformedRequest(details)))
+                .and_then(|record| match record.check() {
+                    Ok(()) => Ok(record),
+                    Err(errors) => Err((Some(record.login), ApiError::from(errors))),
+                });
+
+            (line, record)
+        })
+        .collect()
+}
+
+// Failed rows never stop the others; under `ImportConflictPolicy::Fail` a
+// taken login does.
+pub async fn import_users(
+    user_service: &dyn UserService,
+    format: TransferFormatDTO,
+    input: &str,
+    conflict: ImportConflictPolicy,
+    dry_run: bool,
+    locale: Locale,
+) -> ImportReportDTO {
+    let mut rows = vec![];
+
+    let mut accepted = vec![];
+
+    let mut records = vec![];
+
+    for (line, record) in read_records(format, input) {
+        match record {
+            Ok(record) => {
+                accepted.push((line, record.login.clone()));
+                records.push(record.into());
+            }
+            Err((login, error)) => {
+                rows.push(ImportRowDTO::failed(line, login, &error, locale));
+            }
+        }
+    }
+
+    let result = user_service.import(records, conflict, dry_run).await;
+
+    let applied = result.is_applied();
+
+    for ((line, login), outcome) in accepted.into_iter().zip(result.into_outcomes()) {
+        let row = match outcome {
+            ImportOutcome::Created => {
+                ImportRowDTO::new(line, Some(login), ImportOutcomeDTO::Created)
+            }
+            ImportOutcome::Overwritten => {
+                ImportRowDTO::new(line, Some(login), ImportOutcomeDTO::Overwritten)
+            }
+            ImportOutcome::Skipped => {
+                ImportRowDTO::new(line, Some(login), ImportOutcomeDTO::Skipped)
+            }
+            ImportOutcome::Failed(error) => {
+                ImportRowDTO::failed(line, Some(login), &ApiError::from(error), locale)
+            }
+        };
+
+        rows.push(row);
+    }
+
+    rows.sort_by_key(|row| row.get_line());
+
+    ImportReportDTO::new(dry_run, applied, rows)
+}
+
+pub async fn export_users(
+    user_service: &dyn UserService,
+    format: TransferFormatDTO,
+) -> Result<String, UserServiceGetManyError> {
+    let query = UserQuery::new(None, UserSortField::Id, SortOrder::Asc, 0, usize::MAX);
+
+    let users = user_service
+        .get_many(query)
+        .await?
+        .into_users()
+        .into_users();
+
+    let records = users.into_iter().map(UserRecordDTO::from);
+
+    let output = match format {
+        TransferFormatDTO::Jsonl => records
+            .filter_map(|record| serde_json::to_string(&record).ok())
+            .map(|line| line + "\n")
+            .collect(),
+        TransferFormatDTO::Csv => {
+            let mut writer = WriterBuilder::new().from_writer(vec![]);
+
+            for record in records {
+                // Writing to memory only fails for unserializable values,
+                // which these fields are not.
+                writer.serialize(UserRecordCsvDTO::from(record)).ok();
+            }
+
+            writer
+                .into_inner()
+                .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+                .unwrap_or_default()
+        }
+    };
+
+    Ok(output)
+}
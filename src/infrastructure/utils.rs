@@ -1,8 +1,9 @@
-use crate::infrastructure::constants::ENV_CONFIG;
-use actix_web::http::header::{self, HeaderMap, HeaderValue};
 use rand::Rng;
+use sha256::digest;
 use std::iter;
 
+use crate::core::user::models::PasswordAlgorithm;
+
 pub const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
 
 pub fn generate_salt(length: usize) -> String {
@@ -11,25 +12,18 @@ pub fn generate_salt(length: usize) -> String {
     iter::repeat_with(one_char).take(length).collect()
 }
 
-pub fn insert_access_control_allow_headers(headers: &mut HeaderMap) -> () {
-    headers.insert(
-        header::ACCESS_CONTROL_ALLOW_ORIGIN,
-        HeaderValue::from_str(ENV_CONFIG.clone_access_control_allow_origin().as_str()).unwrap(),
-    );
-
-    headers.insert(
-        header::ACCESS_CONTROL_ALLOW_METHODS,
-        HeaderValue::from_str(ENV_CONFIG.clone_access_control_allow_methods().as_str()).unwrap(),
-    );
-
-    headers.insert(
-        header::ACCESS_CONTROL_ALLOW_HEADERS,
-        HeaderValue::from_str(ENV_CONFIG.clone_access_control_allow_headers().as_str()).unwrap(),
-    );
+pub fn hash_password(password: &str, salt: &str) -> String {
+    digest(format!("{}{}", password, salt))
+}
 
-    headers.insert(
-        header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
-        HeaderValue::from_str(ENV_CONFIG.clone_access_control_allow_credentials().as_str())
-            .unwrap(),
-    );
+pub fn verify_password(
+    algorithm: PasswordAlgorithm,
+    password: &str,
+    hash: &str,
+    salt: &str,
+) -> bool {
+    match algorithm {
+        PasswordAlgorithm::Sha256 => hash_password(password, salt) == hash,
+        PasswordAlgorithm::Bcrypt => bcrypt::verify(password, hash).unwrap_or(false),
+    }
 }
@@ -0,0 +1,145 @@
+use actix_web::dev::Payload;
+use actix_web::{
+    web::{Json, Query},
+    Error as WebActixError, FromRequest, HttpRequest,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::core::user::login::{LoginPolicyError, LOGIN_MAX_LENGTH, LOGIN_MIN_LENGTH};
+
+use super::{
+    errors::ApiError,
+    i18n::{translate, Locale},
+    models::FieldErrorDTO,
+};
+
+// Coded like the matching `validator` rule where there is one.
+pub fn login_error(error: LoginPolicyError) -> ValidationError {
+    let (code, message) = match error {
+        LoginPolicyError::Length => ("length", "validation.login_length"),
+        LoginPolicyError::NotAllowedCharacter => ("login_not_allowed", "login_not_allowed"),
+        LoginPolicyError::MixedScript => ("login_mixed_script", "login_mixed_script"),
+    };
+
+    let mut validation_error = ValidationError::new(code);
+
+    validation_error.message = Some(message.into());
+
+    if error == LoginPolicyError::Length {
+        validation_error.add_param("min".into(), &LOGIN_MIN_LENGTH);
+        validation_error.add_param("max".into(), &LOGIN_MAX_LENGTH);
+    }
+
+    validation_error
+}
+
+pub fn login_errors(error: LoginPolicyError) -> ValidationErrors {
+    let mut errors = ValidationErrors::new();
+
+    errors.add("login", login_error(error));
+
+    errors
+}
+
+fn param_to_string(error: &ValidationError, name: &str) -> Option<String> {
+    match error.params.get(name) {
+        Some(Value::String(value)) => Some(value.clone()),
+        Some(Value::Null) | None => None,
+        Some(value) => Some(value.to_string()),
+    }
+}
+
+// Rule messages are catalog keys; rules without one fall back to the generic
+// `validation.<code>` message.
+fn describe(error: &ValidationError, locale: Locale) -> String {
+    let template = match &error.message {
+        Some(key) => translate(locale, key).unwrap_or(key),
+        None => translate(locale, format!("validation.{}", error.code).as_str())
+            .or_else(|| translate(locale, "validation.invalid"))
+            .unwrap_or_default(),
+    };
+
+    let min = param_to_string(error, "min").unwrap_or_default();
+    let max = param_to_string(error, "max").unwrap_or_default();
+
+    template.replace("{min}", &min).replace("{max}", &max)
+}
+
+pub fn field_errors(errors: &ValidationErrors, locale: Locale) -> Vec<FieldErrorDTO> {
+    let mut fields: Vec<(&str, &Vec<ValidationError>)> =
+        errors.field_errors().into_iter().collect();
+
+    fields.sort_by(|a, b| a.0.cmp(b.0));
+
+    fields
+        .into_iter()
+        .flat_map(|(field, errors)| {
+            errors
+                .iter()
+                .map(move |error| FieldErrorDTO::new(field, &error.code, &describe(error, locale)))
+        })
+        .collect()
+}
+
+fn validate<T: Validate>(value: T) -> Result<T, WebActixError> {
+    match value.validate() {
+        Ok(()) => Ok(value),
+        Err(errors) => Err(ApiError::from(errors).into()),
+    }
+}
+
+pub struct ValidatedJson<T>(T);
+
+impl<T> ValidatedJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> FromRequest for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate + 'static,
+{
+    type Error = WebActixError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let json = Json::<T>::from_request(req, payload);
+
+        Box::pin(async move {
+            let value = json.await?.into_inner();
+
+            validate(value).map(ValidatedJson)
+        })
+    }
+}
+
+pub struct ValidatedQuery<T>(T);
+
+impl<T> ValidatedQuery<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> FromRequest for ValidatedQuery<T>
+where
+    T: DeserializeOwned + Validate + 'static,
+{
+    type Error = WebActixError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let query = Query::<T>::from_request(req, payload);
+
+        Box::pin(async move {
+            let value = query.await?.into_inner();
+
+            validate(value).map(ValidatedQuery)
+        })
+    }
+}
@@ -1,90 +1,139 @@
-mod core;
-mod infrastructure;
-
-use actix_web::dev::{Service, ServiceResponse};
-use actix_web::{
-    error::InternalError,
-    http::Method,
-    main,
-    web::{Data, JsonConfig},
-    App, HttpResponse, HttpServer,
-};
+use actix_web::{main, rt, HttpServer};
 use dotenv::dotenv;
-use std::future::Future;
-use std::pin::Pin;
-use std::sync::{Arc, Mutex};
-
-use crate::core::user::{models::User, repository::UserRepository, service::UserService};
-use crate::infrastructure::user::{repository::MemoryUserRepository, service::UserServiceImp};
-use crate::infrastructure::{
-    constants::ENV_CONFIG, controllers::configure, utils::insert_access_control_allow_headers,
+use opentelemetry::global;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use std::env::args_os;
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+use std::thread::available_parallelism;
+use tracing::{info, warn};
+
+use oped_back::core::user::{repository::UserRepositoryFlushError, service::UserService};
+use oped_back::infrastructure::user::{
+    repository::{build_user_repository, flush_periodically},
+    service::UserServiceImp,
+};
+use oped_back::infrastructure::{
+    app::build_app,
+    config::{exit_with_config_errors, EnvConfig},
+    constants::ENV_CONFIG,
+    logging, shutdown, telemetry,
+    tls::{server_config, ReloadingCertResolver},
 };
 
 #[main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
-    ENV_CONFIG.check();
-
-    let shared_users: Arc<Mutex<Vec<User>>> = Arc::new(Mutex::new(vec![]));
-    let shared_index: Arc<Mutex<i32>> = Arc::new(Mutex::new(1));
-
-    let user_repository: Arc<dyn UserRepository> =
-        Arc::new(MemoryUserRepository::new(shared_users, shared_index));
-
-    let user_service: Arc<dyn UserService> = Arc::new(UserServiceImp::new(user_repository));
-
-    HttpServer::new(move || {
-        let json_config = JsonConfig::default().error_handler(|err, _req| {
-            InternalError::from_response(
-                "",
-                HttpResponse::BadRequest()
-                    .content_type("application/json")
-                    .body(format!(r#"{{"message":"{}"}}"#, err)),
-            )
-            .into()
-        });
-
-        App::new()
-            .app_data(json_config)
-            .app_data(Data::from(user_service.clone()))
-            .configure(configure)
-            .wrap_fn(|service_request, app_routing| -> Pin<Box<dyn Future<Output = Result<ServiceResponse, actix_web::Error>>>> {
-                let http_request = service_request.request();
-                let is_pre_flight = http_request.method() == Method::OPTIONS
-                    && http_request.headers().contains_key("origin")
-                    && http_request
-                        .headers()
-                        .contains_key("access-control-request-method");
-
-                if is_pre_flight {
-                    return Box::pin(async move {
-                        let mut service_response = ServiceResponse::new(
-                            service_request.request().clone(),
-                            HttpResponse::NoContent().finish(),
-                        );
-
-                        let headers = service_response.response_mut().headers_mut();
-
-                        insert_access_control_allow_headers(headers);
-
-                        return Ok(service_response);
-                    });
-                };
-
-                let fut = app_routing.call(service_request);
-
-                Box::pin(async {
-                    let mut service_response: ServiceResponse = fut.await?;
-
-                    let headers = service_response.response_mut().headers_mut();
-
-                    insert_access_control_allow_headers(headers);
-
-                    Ok(service_response)
-                })
-            })
-    })
-    .bind(("127.0.0.1", 25565))?
-    .run()
-    .await
+
+    match EnvConfig::load(args_os()) {
+        Ok(config) => ENV_CONFIG.init(config),
+        Err(errors) => exit_with_config_errors(errors),
+    }
+
+    if let Err(errors) = ENV_CONFIG.check() {
+        exit_with_config_errors(errors);
+    }
+
+    let tracer_provider = telemetry::tracer_provider(
+        ENV_CONFIG.get_tracing_exporter(),
+        &ENV_CONFIG.clone_tracing_otlp_endpoint(),
+        &ENV_CONFIG.clone_tracing_service_name(),
+    )
+    .map_err(|message| Error::new(ErrorKind::InvalidInput, message))?;
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    logging::init(
+        &ENV_CONFIG.clone_log_level(),
+        ENV_CONFIG.get_log_format(),
+        tracer_provider.as_ref().map(telemetry::tracer),
+    );
+
+    let user_repository = build_user_repository(&ENV_CONFIG)
+        .map_err(|message| Error::new(ErrorKind::InvalidData, message))?;
+
+    let shutdown_repository = user_repository.clone();
+
+    let user_service: Arc<dyn UserService> = Arc::new(UserServiceImp::new(
+        user_repository.clone(),
+        ENV_CONFIG.get_login_policy(),
+    ));
+
+    // Signals are handled here rather than by actix, see `stop_on_signal`.
+    let signals = shutdown::ShutdownSignals::register()?;
+
+    let workers = ENV_CONFIG
+        .get_workers()
+        .unwrap_or_else(|| available_parallelism().map_or(1, usize::from));
+
+    let server = HttpServer::new(move || build_app(user_repository.clone(), user_service.clone()))
+        .workers(workers)
+        .shutdown_timeout(ENV_CONFIG.get_shutdown_timeout())
+        .disable_signals();
+
+    let address = (ENV_CONFIG.clone_host(), ENV_CONFIG.get_port());
+
+    let server = if ENV_CONFIG.is_tls_enabled() {
+        let resolver = ReloadingCertResolver::new(
+            ENV_CONFIG.clone_tls_cert_file(),
+            ENV_CONFIG.clone_tls_key_file(),
+        )
+        .map_err(|message| Error::new(ErrorKind::InvalidData, message))?;
+
+        resolver.watch();
+
+        server.bind_rustls(address, server_config(resolver))?
+    } else {
+        server.bind(address)?
+    };
+
+    let server = match ENV_CONFIG.get_tls_redirect_port() {
+        Some(port) => server.bind((ENV_CONFIG.clone_host(), port))?,
+        None => server,
+    };
+
+    info!(
+        host = ENV_CONFIG.clone_host(),
+        port = ENV_CONFIG.get_port(),
+        tls = ENV_CONFIG.is_tls_enabled(),
+        workers,
+        "starting server"
+    );
+
+    if let Some(interval) = ENV_CONFIG.get_storage_snapshot_interval() {
+        rt::spawn(flush_periodically(shutdown_repository.clone(), interval));
+    }
+
+    let server = server.run();
+
+    rt::spawn(shutdown::stop_on_signal(
+        server.handle(),
+        signals,
+        ENV_CONFIG.get_shutdown_delay(),
+    ));
+
+    let result = server.await;
+
+    let storage_flushed = match shutdown_repository.flush().await {
+        Ok(()) => true,
+        Err(UserRepositoryFlushError::UnexpectedError(source)) => {
+            warn!(error = %source, "failed to flush user storage");
+            false
+        }
+    };
+
+    let spans_flushed = match tracer_provider.map(|provider| provider.shutdown()) {
+        Some(Err(error)) => {
+            warn!(error = %error, "failed to flush spans");
+            false
+        }
+        _ => true,
+    };
+
+    info!(
+        dropped_requests = shutdown::dropped_requests(),
+        storage_flushed, spans_flushed, "shutdown complete"
+    );
+
+    result
 }
@@ -0,0 +1,243 @@
+use actix_web::{
+    http::{header, StatusCode},
+    test::TestRequest,
+};
+
+use crate::harness::{spawn, API, FOREIGN_ORIGIN};
+
+const CREATED_AT: &str = "2023-05-01T12:00:00Z";
+
+const HASH: &str = "5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8";
+
+fn jsonl(logins: &[&str]) -> String {
+    logins
+        .iter()
+        .map(|login| {
+            format!(
+                "{{\"login\":\"{}\",\"password_algorithm\":\"sha256\",\"password_hash\":\"{}\",\
+                 \"created_at\":\"{}\"}}\n",
+                login, HASH, CREATED_AT
+            )
+        })
+        .collect()
+}
+
+#[actix_web::test]
+async fn imports_users() {
+    let mut app = spawn().await;
+
+    app.sign_up("root", "secret1").await;
+
+    app.grant_role("root", "admin").await;
+
+    let res = app
+        .post_body(
+            "/admin/users/import",
+            "application/x-ndjson",
+            &(jsonl(&["alice", "bob", "root", "x", &"ﬃ".repeat(11)]) + "{}\n"),
+        )
+        .await;
+
+    assert_eq!(res.status(), StatusCode::OK, "{}", res.text());
+
+    let report = res.json();
+
+    // The taken `root` stops a `fail` import, the default policy.
+    assert_eq!(report["applied"], false);
+    assert_eq!(report["failed"], 4);
+    assert_eq!(report["rows"][2]["code"], "login_already_used");
+    assert_eq!(report["rows"][3]["code"], "validation_failed");
+    assert_eq!(report["rows"][4]["code"], "validation_failed");
+    assert_eq!(report["rows"][5]["code"], "malformed_request");
+
+    app.get("/users/alice")
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "user_not_found");
+
+    let res = app
+        .post_body(
+            "/admin/users/import?conflict=skip",
+            "application/x-ndjson",
+            &jsonl(&["alice", "bob", "root"]),
+        )
+        .await;
+
+    let report = res.json();
+
+    assert_eq!(report["applied"], true);
+    assert_eq!(report["created"], 2);
+    assert_eq!(report["skipped"], 1);
+
+    assert_eq!(app.get("/users/bob").await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn imports_csv_as_dry_run() {
+    let mut app = spawn().await;
+
+    app.sign_up("root", "secret1").await;
+
+    app.grant_role("root", "admin").await;
+
+    let csv = format!(
+        "login,password_algorithm,password_hash,roles,created_at\n\
+         alice,sha256,{},admin editor,{}\n",
+        HASH, CREATED_AT
+    );
+
+    let res = app
+        .post_body(
+            "/admin/users/import?format=csv&dry_run=true",
+            "text/csv",
+            &csv,
+        )
+        .await;
+
+    assert_eq!(res.status(), StatusCode::OK, "{}", res.text());
+
+    let report = res.json();
+
+    assert_eq!(report["dry_run"], true);
+    assert_eq!(report["applied"], false);
+    assert_eq!(report["created"], 1);
+
+    app.get("/users/alice")
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "user_not_found");
+}
+
+#[actix_web::test]
+async fn import_rejects_malformed_query() {
+    let mut app = spawn().await;
+
+    app.sign_up("root", "secret1").await;
+
+    app.grant_role("root", "admin").await;
+
+    app.post_body("/admin/users/import?format=xml", "text/plain", "")
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "malformed_request");
+}
+
+#[actix_web::test]
+async fn import_requires_admin() {
+    let mut app = spawn().await;
+
+    app.post_body(
+        "/admin/users/import",
+        "application/x-ndjson",
+        &jsonl(&["alice"]),
+    )
+    .await
+    .assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+
+    app.sign_up("bob", "secret1").await;
+
+    app.post_body(
+        "/admin/users/import",
+        "application/x-ndjson",
+        &jsonl(&["alice"]),
+    )
+    .await
+    .assert_error(StatusCode::FORBIDDEN, "forbidden");
+
+    app.get("/users/alice")
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "user_not_found");
+}
+
+#[actix_web::test]
+async fn import_rejects_banned_admin() {
+    let mut app = spawn().await;
+
+    app.sign_up("root", "secret1").await;
+
+    app.grant_role("root", "admin").await;
+
+    app.ban("root").await;
+
+    app.post_body(
+        "/admin/users/import",
+        "application/x-ndjson",
+        &jsonl(&["alice"]),
+    )
+    .await
+    .assert_error(StatusCode::FORBIDDEN, "account_banned");
+}
+
+#[actix_web::test]
+async fn import_rejects_foreign_origin() {
+    let mut app = spawn().await;
+
+    app.sign_up("root", "secret1").await;
+
+    app.grant_role("root", "admin").await;
+
+    app.send(
+        TestRequest::post()
+            .uri(&format!("{}/admin/users/import", API))
+            .insert_header((header::ORIGIN, FOREIGN_ORIGIN))
+            .insert_header((header::CONTENT_TYPE, "application/x-ndjson"))
+            .set_payload(jsonl(&["alice"])),
+    )
+    .await
+    .assert_error(StatusCode::FORBIDDEN, "cross_site_request");
+}
+
+#[actix_web::test]
+async fn exports_users() {
+    let mut app = spawn().await;
+
+    app.sign_up("root", "secret1").await;
+
+    app.grant_role("root", "admin").await;
+
+    app.register("alice", "secret1").await;
+
+    let res = app.get("/admin/users/export?format=csv").await;
+
+    assert_eq!(res.status(), StatusCode::OK, "{}", res.text());
+    assert_eq!(res.header("content-type"), Some("text/csv; charset=utf-8"));
+    assert_eq!(
+        res.header("content-disposition"),
+        Some("attachment; filename=\"users.csv\"")
+    );
+
+    let csv = res.text();
+
+    let mut lines = csv.lines();
+
+    assert!(lines
+        .next()
+        .is_some_and(|header| header.starts_with("login,")));
+    assert!(lines.next().is_some_and(|row| row.starts_with("root,")));
+    assert!(lines.next().is_some_and(|row| row.starts_with("alice,")));
+
+    let res = app.get("/admin/users/export").await;
+
+    assert_eq!(res.header("content-type"), Some("application/x-ndjson"));
+
+    let logins: Vec<String> = res
+        .text()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).expect("a JSON line"))
+        .map(|record| record["login"].as_str().unwrap_or_default().to_owned())
+        .collect();
+
+    assert_eq!(logins, ["root", "alice"]);
+}
+
+#[actix_web::test]
+async fn export_requires_admin() {
+    let mut app = spawn().await;
+
+    app.get("/admin/users/export")
+        .await
+        .assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+
+    app.sign_up("bob", "secret1").await;
+
+    app.get("/admin/users/export")
+        .await
+        .assert_error(StatusCode::FORBIDDEN, "forbidden");
+}
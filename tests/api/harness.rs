@@ -0,0 +1,329 @@
+//! Boots the whole application in-process, as `main` does, on a fresh
+//! repository. Requests go straight to the service, never over a socket.
+
+use actix_http::Request;
+use actix_web::{
+    body::MessageBody,
+    cookie::Cookie,
+    dev::{Service, ServiceResponse},
+    http::{
+        header::{self, HeaderMap},
+        StatusCode,
+    },
+    test::{self, TestRequest},
+    web::Bytes,
+    Error,
+};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::{Arc, Once};
+
+use oped_back::core::user::{models::User, repository::UserRepository, service::UserService};
+use oped_back::infrastructure::{
+    app::build_app,
+    config::EnvConfig,
+    constants::{ENV_CONFIG, KEY_RING},
+    user::{repository::build_user_repository, service::UserServiceImp},
+};
+
+/// The only origin `cors.allow_origin` lists; helpers send it by default.
+pub const ORIGIN: &str = "http://localhost";
+
+/// An origin the server does not trust.
+pub const FOREIGN_ORIGIN: &str = "http://evil.example";
+
+pub const API: &str = "/api/v1";
+
+/// Fixed so failures reproduce; only ever signs tokens for these tests.
+const JWT_SECRET: &str = "3f8a61c2d94e07b5a1c6e2f9d0b74a8e5c13f6d27b9e40a8c5d1f3b6e92a07c4";
+
+pub const METRICS_TOKEN: &str = "b71e4d09a3c85f26e1d7094b3a6c8f52d0e9a17b4c63f8e2a5d1907c3b8e6f4a";
+
+static CONFIG: Once = Once::new();
+
+/// `ENV_CONFIG` is process-wide, so every test shares this configuration.
+fn init_config() {
+    CONFIG.call_once(|| {
+        let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("api.toml");
+
+        // Secrets have no flag; they only come from files or the environment.
+        std::fs::write(
+            &path,
+            format!(
+                "[jwt]\nsecret = \"{}\"\n[metrics]\ntoken = \"{}\"\n",
+                JWT_SECRET, METRICS_TOKEN
+            ),
+        )
+        .expect("test configuration is written");
+
+        let args = [
+            "oped-back",
+            "--config",
+            path.to_str().expect("path is UTF-8"),
+            "--jwt-domain",
+            "localhost",
+            "--cors-allow-origin",
+            ORIGIN,
+            "--cors-allow-methods",
+            "GET, POST",
+            "--cors-allow-headers",
+            "content-type",
+            "--cors-allow-credentials",
+            "true",
+            "--default-locale",
+            "en",
+            "--login-policy",
+            "unicode",
+        ];
+
+        let config = EnvConfig::load(args).unwrap_or_else(|errors| panic!("{:?}", errors));
+
+        ENV_CONFIG.init(config);
+
+        ENV_CONFIG
+            .check()
+            .unwrap_or_else(|errors| panic!("{:?}", errors));
+    });
+}
+
+/// A response read to the end.
+pub struct TestResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    cookies: Vec<Cookie<'static>>,
+    body: Bytes,
+}
+
+impl TestResponse {
+    async fn read<B: MessageBody>(res: ServiceResponse<B>) -> Self {
+        let status = res.status();
+
+        let headers = res.headers().clone();
+
+        let cookies = res
+            .response()
+            .cookies()
+            .map(|cookie| cookie.into_owned())
+            .collect();
+
+        let body = test::read_body(res).await;
+
+        Self {
+            status,
+            headers,
+            cookies,
+            body,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    pub fn cookie(&self, name: &str) -> Option<&Cookie<'static>> {
+        self.cookies.iter().find(|cookie| cookie.name() == name)
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body)
+            .unwrap_or_else(|error| panic!("{}: {}", error, self.text()))
+    }
+
+    /// `code` of an error body.
+    pub fn code(&self) -> String {
+        self.json()["code"].as_str().unwrap_or_default().to_owned()
+    }
+
+    /// Codes of the per-field errors of a `validation_failed` body.
+    pub fn field_codes(&self, field: &str) -> Vec<String> {
+        self.json()["errors"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|error| error["field"] == field)
+            .filter_map(|error| error["code"].as_str().map(str::to_owned))
+            .collect()
+    }
+
+    /// Asserts the status and error code, showing the body when they differ.
+    pub fn assert_error(&self, status: StatusCode, code: &str) {
+        assert_eq!(
+            (self.status, self.code().as_str()),
+            (status, code),
+            "{}",
+            self.text()
+        );
+    }
+}
+
+/// The application plus the session cookie of whoever signed in last.
+pub struct TestApp<S> {
+    service: S,
+    user_repository: Arc<dyn UserRepository>,
+    session: Option<String>,
+}
+
+/// A new application with no users.
+pub async fn spawn(
+) -> TestApp<impl Service<Request, Response = ServiceResponse<impl MessageBody>, Error = Error>> {
+    init_config();
+
+    // No `storage.data_dir`: a memory repository behind the lookup cache.
+    let user_repository =
+        build_user_repository(&ENV_CONFIG).unwrap_or_else(|message| panic!("{}", message));
+
+    let user_service: Arc<dyn UserService> = Arc::new(UserServiceImp::new(
+        user_repository.clone(),
+        ENV_CONFIG.get_login_policy(),
+    ));
+
+    let service = test::init_service(build_app(user_repository.clone(), user_service)).await;
+
+    TestApp {
+        service,
+        user_repository,
+        session: None,
+    }
+}
+
+impl<S, B> TestApp<S>
+where
+    S: Service<Request, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    /// Sends `req` with the session cookie, if any, and nothing else added.
+    pub async fn send(&self, req: TestRequest) -> TestResponse {
+        let req = match &self.session {
+            Some(session) => req.cookie(Cookie::new("jwt", session.clone())),
+            None => req,
+        };
+
+        let res = test::call_service(&self.service, req.to_request()).await;
+
+        TestResponse::read(res).await
+    }
+
+    pub async fn get(&self, path: &str) -> TestResponse {
+        self.send(TestRequest::get().uri(&format!("{}{}", API, path)))
+            .await
+    }
+
+    /// POSTs `body` as JSON from the trusted origin.
+    pub async fn post_json(&self, path: &str, body: Value) -> TestResponse {
+        self.send(
+            TestRequest::post()
+                .uri(&format!("{}{}", API, path))
+                .insert_header((header::ORIGIN, ORIGIN))
+                .set_json(body),
+        )
+        .await
+    }
+
+    /// POSTs a raw body from the trusted origin.
+    pub async fn post_body(&self, path: &str, content_type: &str, body: &str) -> TestResponse {
+        self.send(
+            TestRequest::post()
+                .uri(&format!("{}{}", API, path))
+                .insert_header((header::ORIGIN, ORIGIN))
+                .insert_header((header::CONTENT_TYPE, content_type))
+                .set_payload(body.to_owned()),
+        )
+        .await
+    }
+
+    pub async fn register(&self, login: &str, password: &str) -> TestResponse {
+        self.post_json(
+            "/users/registration",
+            json!({ "login": login, "password": password }),
+        )
+        .await
+    }
+
+    /// Signs in; on success later requests carry the new session cookie.
+    pub async fn login(&mut self, login: &str, password: &str) -> TestResponse {
+        let res = self
+            .post_json(
+                "/users/login",
+                json!({ "login": login, "password": password }),
+            )
+            .await;
+
+        if let Some(cookie) = res.cookie("jwt") {
+            self.session = Some(cookie.value().to_owned());
+        }
+
+        res
+    }
+
+    /// Registers `login` and signs in as them.
+    pub async fn sign_up(&mut self, login: &str, password: &str) {
+        let res = self.register(login, password).await;
+
+        assert_eq!(res.status(), StatusCode::OK, "{}", res.text());
+
+        let res = self.login(login, password).await;
+
+        assert_eq!(res.status(), StatusCode::OK, "{}", res.text());
+    }
+
+    /// Signs out; on success later requests carry no session cookie.
+    pub async fn logout(&mut self) -> TestResponse {
+        let res = self.post_json("/users/logout", json!({})).await;
+
+        if res.status() == StatusCode::OK {
+            self.session = None;
+        }
+
+        res
+    }
+
+    /// Replaces the session cookie, e.g. with a forged token.
+    pub fn set_session(&mut self, session: Option<&str>) {
+        self.session = session.map(str::to_owned);
+    }
+
+    /// Signs `claims` with the server's current key, e.g. to forge a
+    /// session that has expired.
+    pub fn sign_session(&mut self, claims: Value) {
+        self.session = Some(KEY_RING.sign(&claims));
+    }
+
+    /// Grants `role` straight in the repository, as `oped-admin` does.
+    pub async fn grant_role(&self, login: &str, role: &str) {
+        let mut user = self.stored_user(login).await;
+
+        user.grant_role(role.to_owned());
+
+        self.user_repository
+            .update(user)
+            .await
+            .expect("user is updated");
+    }
+
+    /// Bans `login` straight in the repository, as `oped-admin` does.
+    pub async fn ban(&self, login: &str) {
+        let mut user = self.stored_user(login).await;
+
+        user.set_banned(true);
+
+        self.user_repository
+            .update(user)
+            .await
+            .expect("user is updated");
+    }
+
+    async fn stored_user(&self, login: &str) -> User {
+        self.user_repository
+            .select_one_by_login(login.to_owned())
+            .await
+            .unwrap_or_else(|error| panic!("`{}` is not stored: {:?}", login, error))
+    }
+}
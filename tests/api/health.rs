@@ -0,0 +1,24 @@
+use actix_web::{http::StatusCode, test::TestRequest};
+use serde_json::json;
+
+use crate::harness::spawn;
+
+#[actix_web::test]
+async fn readiness_reports_only_component_status() {
+    let app = spawn().await;
+
+    let res = app.send(TestRequest::get().uri("/readyz")).await;
+
+    assert_eq!(res.status(), StatusCode::OK, "{}", res.text());
+    assert_eq!(
+        res.json(),
+        json!({
+            "status": "up",
+            "components": {
+                "config": { "status": "up" },
+                "server": { "status": "up" },
+                "storage": { "status": "up" },
+            },
+        })
+    );
+}
@@ -0,0 +1,8 @@
+//! Every route of `infrastructure::user::controllers::configure`, plus the
+//! health checks and metrics, through the full application.
+
+mod admin;
+mod harness;
+mod health;
+mod metrics;
+mod users;
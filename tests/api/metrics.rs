@@ -0,0 +1,45 @@
+use actix_web::{
+    http::{header, Method, StatusCode},
+    test::TestRequest,
+};
+
+use crate::harness::{spawn, METRICS_TOKEN};
+
+fn scrape(token: &str) -> TestRequest {
+    TestRequest::get()
+        .uri("/metrics")
+        .insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+}
+
+#[actix_web::test]
+async fn metrics_require_token() {
+    let app = spawn().await;
+
+    app.send(TestRequest::get().uri("/metrics"))
+        .await
+        .assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+
+    app.send(scrape(&METRICS_TOKEN[1..]))
+        .await
+        .assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+
+    let res = app.send(scrape(METRICS_TOKEN)).await;
+
+    assert_eq!(res.status(), StatusCode::OK, "{}", res.text());
+    assert!(res.text().contains("http_requests_total"));
+}
+
+#[actix_web::test]
+async fn metrics_label_unknown_methods_as_other() {
+    let app = spawn().await;
+
+    let method = Method::from_bytes(b"BREW").expect("a valid method");
+
+    app.send(TestRequest::default().method(method).uri("/healthz"))
+        .await;
+
+    let metrics = app.send(scrape(METRICS_TOKEN)).await.text();
+
+    assert!(!metrics.contains("method=\"BREW\""));
+    assert!(metrics.contains("method=\"other\""));
+}
@@ -0,0 +1,400 @@
+use actix_web::{
+    http::{header, StatusCode},
+    test::TestRequest,
+};
+use serde_json::json;
+
+use crate::harness::{spawn, API, FOREIGN_ORIGIN};
+
+#[actix_web::test]
+async fn registers_user() {
+    let app = spawn().await;
+
+    let res = app.register("alice", "secret1").await;
+
+    assert_eq!(res.status(), StatusCode::OK, "{}", res.text());
+
+    let body = res.json();
+
+    assert_eq!(body["login"], "alice");
+    assert!(body["id"].is_i64());
+    assert!(body.get("hash").is_none());
+    assert!(res.cookie("jwt").is_none());
+}
+
+#[actix_web::test]
+async fn registration_rejects_taken_login() {
+    let app = spawn().await;
+
+    app.register("alice", "secret1").await;
+
+    app.register("ALICE", "secret2")
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "login_already_used");
+}
+
+#[actix_web::test]
+async fn registration_validates_fields() {
+    let app = spawn().await;
+
+    let res = app.post_json("/users/registration", json!({})).await;
+
+    res.assert_error(StatusCode::BAD_REQUEST, "validation_failed");
+    assert_eq!(res.field_codes("login"), ["required"]);
+    assert_eq!(res.field_codes("password"), ["required"]);
+
+    let res = app.register("al", "x").await;
+
+    res.assert_error(StatusCode::BAD_REQUEST, "validation_failed");
+    assert_eq!(res.field_codes("login"), ["length"]);
+    assert_eq!(res.field_codes("password"), ["length"]);
+
+    let res = app.register("al ice", "secret1").await;
+
+    assert!(res.field_codes("login").contains(&"regex".to_owned()));
+}
+
+#[actix_web::test]
+async fn registration_checks_length_of_normalized_login() {
+    let app = spawn().await;
+
+    // "ﬃ" is one character that NFKC turns into three.
+    let res = app.register(&"ﬃ".repeat(11), "secret1").await;
+
+    res.assert_error(StatusCode::BAD_REQUEST, "validation_failed");
+    assert_eq!(res.field_codes("login"), ["length"]);
+
+    let res = app.register(&"ﬃ".repeat(10), "secret1").await;
+
+    assert_eq!(res.status(), StatusCode::OK, "{}", res.text());
+    assert_eq!(res.json()["login"], "ffi".repeat(10));
+}
+
+#[actix_web::test]
+async fn registration_applies_login_policy() {
+    let app = spawn().await;
+
+    let res = app.register("al!ce", "secret1").await;
+
+    res.assert_error(StatusCode::BAD_REQUEST, "validation_failed");
+    assert_eq!(res.field_codes("login"), ["login_not_allowed"]);
+
+    // Latin with a Cyrillic "а".
+    let res = app.register("аlice", "secret1").await;
+
+    res.assert_error(StatusCode::BAD_REQUEST, "validation_failed");
+    assert_eq!(res.field_codes("login"), ["login_mixed_script"]);
+}
+
+#[actix_web::test]
+async fn registration_rejects_malformed_json() {
+    let app = spawn().await;
+
+    app.post_body("/users/registration", "application/json", "{\"login\":")
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "malformed_request");
+}
+
+#[actix_web::test]
+async fn registration_rejects_foreign_origin() {
+    let mut app = spawn().await;
+
+    app.sign_up("alice", "secret1").await;
+
+    app.send(
+        TestRequest::post()
+            .uri(&format!("{}/users/registration", API))
+            .insert_header((header::ORIGIN, FOREIGN_ORIGIN))
+            .set_json(json!({ "login": "bob", "password": "secret1" })),
+    )
+    .await
+    .assert_error(StatusCode::FORBIDDEN, "cross_site_request");
+}
+
+#[actix_web::test]
+async fn login_sets_session_cookie() {
+    let mut app = spawn().await;
+
+    app.register("alice", "secret1").await;
+
+    let res = app.login("Alice", "secret1").await;
+
+    assert_eq!(res.status(), StatusCode::OK, "{}", res.text());
+
+    let cookie = res.cookie("jwt").expect("session cookie is set");
+
+    assert!(!cookie.value().is_empty());
+    assert_eq!(cookie.http_only(), Some(true));
+    assert_eq!(cookie.secure(), Some(true));
+    assert_eq!(cookie.path(), Some("/"));
+    assert_eq!(
+        cookie.max_age(),
+        Some(actix_web::cookie::time::Duration::days(1))
+    );
+}
+
+#[actix_web::test]
+async fn login_rejects_wrong_credentials() {
+    let mut app = spawn().await;
+
+    app.register("alice", "secret1").await;
+
+    let res = app.login("alice", "secret2").await;
+
+    res.assert_error(StatusCode::BAD_REQUEST, "wrong_credentials");
+    assert!(res.cookie("jwt").is_none());
+
+    app.login("bob", "secret1")
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "wrong_credentials");
+}
+
+#[actix_web::test]
+async fn login_validates_fields() {
+    let mut app = spawn().await;
+
+    app.post_json("/users/login", json!({ "login": "alice" }))
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "validation_failed");
+
+    app.post_body("/users/login", "application/json", "[]")
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "malformed_request");
+
+    assert!(app.login("", "").await.cookie("jwt").is_none());
+}
+
+#[actix_web::test]
+async fn login_checks_length_of_normalized_login() {
+    let mut app = spawn().await;
+
+    // 32 characters as sent, 16 once NFKC composes each "é".
+    let login = "e\u{301}".repeat(16);
+
+    app.sign_up(&login, "secret1").await;
+
+    let res = app.login(&"ﬃ".repeat(11), "secret1").await;
+
+    res.assert_error(StatusCode::BAD_REQUEST, "validation_failed");
+    assert_eq!(res.field_codes("login"), ["length"]);
+}
+
+#[actix_web::test]
+async fn login_rejects_banned_user() {
+    let mut app = spawn().await;
+
+    app.register("alice", "secret1").await;
+
+    app.ban("alice").await;
+
+    app.login("alice", "secret1")
+        .await
+        .assert_error(StatusCode::FORBIDDEN, "account_banned");
+}
+
+#[actix_web::test]
+async fn profile_shows_signed_in_user() {
+    let mut app = spawn().await;
+
+    app.sign_up("alice", "secret1").await;
+
+    app.grant_role("alice", "editor").await;
+
+    let res = app.get("/users/profile").await;
+
+    assert_eq!(res.status(), StatusCode::OK, "{}", res.text());
+
+    let body = res.json();
+
+    assert_eq!(body["login"], "alice");
+    assert_eq!(body["roles"], json!(["editor"]));
+}
+
+#[actix_web::test]
+async fn profile_requires_session() {
+    let mut app = spawn().await;
+
+    app.get("/users/profile")
+        .await
+        .assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+
+    app.set_session(Some("not-a-token"));
+
+    app.get("/users/profile")
+        .await
+        .assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+}
+
+#[actix_web::test]
+async fn profile_rejects_expired_session() {
+    let mut app = spawn().await;
+
+    let id = app.register("alice", "secret1").await.json()["id"].clone();
+
+    let now = jsonwebtoken::get_current_timestamp();
+
+    app.sign_session(json!({ "user_id": id, "iat": now, "exp": now + 3600 }));
+
+    assert_eq!(app.get("/users/profile").await.status(), StatusCode::OK);
+
+    for claims in [
+        json!({ "user_id": id, "iat": now - 7200, "exp": now - 3600 }),
+        json!({ "user_id": id, "iat": now }),
+        json!({ "user_id": id, "exp": now + 3600 }),
+        json!({ "user_id": id, "iat": now + 3600, "exp": now + 7200 }),
+    ] {
+        app.sign_session(claims.clone());
+
+        let res = app.get("/users/profile").await;
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{}", claims);
+    }
+}
+
+#[actix_web::test]
+async fn profile_rejects_banned_user() {
+    let mut app = spawn().await;
+
+    app.sign_up("alice", "secret1").await;
+
+    app.ban("alice").await;
+
+    app.get("/users/profile")
+        .await
+        .assert_error(StatusCode::FORBIDDEN, "account_banned");
+}
+
+#[actix_web::test]
+async fn logout_clears_session_cookie() {
+    let mut app = spawn().await;
+
+    app.sign_up("alice", "secret1").await;
+
+    let res = app.logout().await;
+
+    assert_eq!(res.status(), StatusCode::OK, "{}", res.text());
+
+    let cookie = res.cookie("jwt").expect("session cookie is cleared");
+
+    assert_eq!(cookie.value(), "");
+    assert_eq!(
+        cookie.max_age(),
+        Some(actix_web::cookie::time::Duration::ZERO)
+    );
+
+    app.get("/users/profile")
+        .await
+        .assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+}
+
+#[actix_web::test]
+async fn logout_requires_session() {
+    let mut app = spawn().await;
+
+    app.logout()
+        .await
+        .assert_error(StatusCode::UNAUTHORIZED, "unauthorized");
+}
+
+#[actix_web::test]
+async fn logout_rejects_foreign_origin() {
+    let mut app = spawn().await;
+
+    app.sign_up("alice", "secret1").await;
+
+    app.send(
+        TestRequest::post()
+            .uri(&format!("{}/users/logout", API))
+            .insert_header((header::ORIGIN, FOREIGN_ORIGIN)),
+    )
+    .await
+    .assert_error(StatusCode::FORBIDDEN, "cross_site_request");
+
+    assert_eq!(app.get("/users/profile").await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn lists_users_by_page() {
+    let app = spawn().await;
+
+    for login in ["alice", "bob", "carol"] {
+        app.register(login, "secret1").await;
+    }
+
+    let res = app.get("/users?per_page=2").await;
+
+    assert_eq!(res.status(), StatusCode::OK, "{}", res.text());
+
+    let body = res.json();
+
+    assert_eq!(body["total"], 3);
+    assert_eq!(body["items"].as_array().map(Vec::len), Some(2));
+    assert_eq!(body["items"][0]["login"], "alice");
+
+    let next = body["next"].as_str().expect("a next page").to_owned();
+
+    let res = app.send(TestRequest::get().uri(&next)).await.json();
+
+    assert_eq!(res["items"][0]["login"], "carol");
+    assert!(res["next"].is_null());
+}
+
+#[actix_web::test]
+async fn lists_users_matching_search() {
+    let app = spawn().await;
+
+    for login in ["alice", "alina", "bob"] {
+        app.register(login, "secret1").await;
+    }
+
+    let body = app
+        .get("/users?search=ALI&sort=login&order=desc")
+        .await
+        .json();
+
+    assert_eq!(body["total"], 2);
+    assert_eq!(body["items"][0]["login"], "alina");
+    assert_eq!(body["items"][1]["login"], "alice");
+}
+
+#[actix_web::test]
+async fn listing_validates_query() {
+    let app = spawn().await;
+
+    let res = app.get("/users?per_page=0").await;
+
+    res.assert_error(StatusCode::BAD_REQUEST, "validation_failed");
+    assert_eq!(res.field_codes("per_page"), ["range"]);
+
+    let res = app
+        .get("/users?page=18446744073709551615&per_page=100")
+        .await;
+
+    res.assert_error(StatusCode::BAD_REQUEST, "validation_failed");
+    assert_eq!(res.field_codes("page"), ["range"]);
+
+    let body = app.get("/users?page=1000000&per_page=100").await.json();
+
+    assert_eq!(body["items"], json!([]));
+    assert!(body["next"].is_null());
+
+    app.get("/users?page=first")
+        .await
+        .assert_error(StatusCode::BAD_REQUEST, "malformed_request");
+}
+
+#[actix_web::test]
+async fn gets_user_by_login() {
+    let app = spawn().await;
+
+    app.register("alice", "secret1").await;
+
+    let res = app.get("/users/ALICE").await;
+
+    assert_eq!(res.status(), StatusCode::OK, "{}", res.text());
+    assert_eq!(res.json()["login"], "alice");
+
+    app.get("/users/bob")
+        .await
+        .assert_error(StatusCode::NOT_FOUND, "user_not_found");
+}
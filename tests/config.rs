@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+
+use oped_back::infrastructure::config::{ConfigError, EnvConfig};
+
+const SECRET: &str = "3f8a61c2d94e07b5a1c6e2f9d0b74a8e5c13f6d27b9e40a8c5d1f3b6e92a07c4";
+
+/// Contains commas, which used to split it into separate keys.
+const OLD_SECRET: &str = "9,c2e71f0b4d85a63,e1f7c94b20d8a56f3e1c7b92d04a8f6e5c3b1a79d2e0f4";
+
+fn write(name: &str, content: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+
+    std::fs::write(&path, content).expect("file is written");
+
+    path
+}
+
+fn parse(name: &str, config: &str) -> EnvConfig {
+    let path = write(name, config);
+
+    let args = [
+        "oped-back",
+        "--config",
+        path.to_str().expect("path is UTF-8"),
+        "--jwt-domain",
+        "localhost",
+        "--cors-allow-origin",
+        "http://localhost",
+        "--cors-allow-methods",
+        "GET",
+        "--cors-allow-headers",
+        "content-type",
+        "--cors-allow-credentials",
+        "true",
+    ];
+
+    EnvConfig::load(args).unwrap_or_else(|errors| panic!("{:?}", errors))
+}
+
+fn load(name: &str, config: &str) -> EnvConfig {
+    let config = parse(name, config);
+
+    config
+        .check()
+        .unwrap_or_else(|errors| panic!("{:?}", errors));
+
+    config
+}
+
+fn expected() -> Vec<(String, String)> {
+    vec![
+        ("2023-01".to_owned(), OLD_SECRET.to_owned()),
+        ("2022-12".to_owned(), SECRET.to_owned()),
+    ]
+}
+
+#[test]
+fn reads_previous_keys_from_array() {
+    let config = load(
+        "array.toml",
+        &format!(
+            "[jwt]\nsecret = \"{}\"\nprevious_keys = [\"2023-01:{}\", \"2022-12:{}\"]\n",
+            SECRET, OLD_SECRET, SECRET
+        ),
+    );
+
+    assert_eq!(config.clone_jwt_previous_keys(), expected());
+}
+
+#[test]
+fn reads_previous_keys_from_file_by_line() {
+    let keys = write(
+        "previous_keys",
+        &format!("2023-01:{}\n\n2022-12:{}\n", OLD_SECRET, SECRET),
+    );
+
+    let config = load(
+        "file.toml",
+        &format!(
+            "[jwt]\nsecret = \"{}\"\nprevious_keys_file = \"{}\"\n",
+            SECRET,
+            keys.display()
+        ),
+    );
+
+    assert_eq!(config.clone_jwt_previous_keys(), expected());
+}
+
+#[test]
+fn rejects_secret_of_32_hex_digits_by_length() {
+    let config = parse(
+        "short_secret.toml",
+        "[jwt]\nsecret = \"3f8a61c2d94e07b5a1c6e2f9d0b74a8e\"\n",
+    );
+
+    let errors = config.check().expect_err("secret is too short");
+
+    assert!(
+        matches!(
+            errors.as_slice(),
+            [ConfigError::Invalid { key: "jwt.secret", message, .. }]
+                if message.starts_with("must be at least 64 characters long")
+        ),
+        "{:?}",
+        errors
+    );
+}
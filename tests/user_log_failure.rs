@@ -0,0 +1,109 @@
+//! Lowers the process-wide file size limit, so it runs in a binary of its own.
+
+#![cfg(unix)]
+
+use chrono::Utc;
+use std::path::PathBuf;
+
+use oped_back::core::user::{
+    models::{PasswordAlgorithm, User, UserRecord},
+    repository::{UserRepository, UserRepositoryInsertError, UserRepositoryUpdateError},
+};
+use oped_back::infrastructure::user::repository::MemoryUserRepository;
+
+fn set_file_size_limit(limit: libc::rlim_t) {
+    let limit = libc::rlimit {
+        rlim_cur: limit,
+        rlim_max: libc::RLIM_INFINITY,
+    };
+
+    // Writes past the limit then fail with EFBIG instead of raising SIGXFSZ.
+    let result = unsafe {
+        libc::signal(libc::SIGXFSZ, libc::SIG_IGN);
+        libc::setrlimit(libc::RLIMIT_FSIZE, &limit)
+    };
+
+    assert_eq!(result, 0, "file size limit is set");
+}
+
+#[actix_web::test]
+async fn failed_log_write_leaves_users_unchanged() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("user_log_failure");
+
+    std::fs::remove_dir_all(&dir).ok();
+
+    let repository = MemoryUserRepository::open(&dir).expect("data directory opens");
+
+    let id = repository
+        .insert("alice".to_owned(), "hash".to_owned(), "salt".to_owned())
+        .await
+        .expect("inserted");
+
+    let log_len = std::fs::metadata(dir.join("users.log"))
+        .expect("log exists")
+        .len();
+
+    let renamed = User::from_record(
+        id,
+        UserRecord::new(
+            "alicia".to_owned(),
+            PasswordAlgorithm::Sha256,
+            "hash".to_owned(),
+            "salt".to_owned(),
+            vec![],
+            Utc::now(),
+            false,
+        ),
+    );
+
+    set_file_size_limit(log_len);
+
+    let updated = repository.update(renamed).await;
+
+    let inserted = repository
+        .insert("bob".to_owned(), "hash".to_owned(), "salt".to_owned())
+        .await;
+
+    set_file_size_limit(libc::RLIM_INFINITY);
+
+    assert!(matches!(
+        updated,
+        Err(UserRepositoryUpdateError::UnexpectedError(_))
+    ));
+    assert!(matches!(
+        inserted,
+        Err(UserRepositoryInsertError::UnexpectedError(_))
+    ));
+
+    let user = repository
+        .select_one_by_login("alice".to_owned())
+        .await
+        .expect("still found by the old login");
+
+    assert_eq!(user.get_id(), id);
+
+    for login in ["alicia", "bob"] {
+        assert!(repository
+            .select_one_by_login(login.to_owned())
+            .await
+            .is_err());
+    }
+
+    assert!(matches!(
+        repository
+            .insert("alice".to_owned(), "hash".to_owned(), "salt".to_owned())
+            .await,
+        Err(UserRepositoryInsertError::LoginAlreadyUsed)
+    ));
+
+    drop(repository);
+
+    let repository = MemoryUserRepository::open(&dir).expect("data directory reopens");
+
+    let user = repository
+        .select_one_by_login("alice".to_owned())
+        .await
+        .expect("recovered under the old login");
+
+    assert_eq!(user.get_id(), id);
+}
@@ -0,0 +1,121 @@
+//! What `CachedUserRepository` adds on top of the conformance suite: users
+//! are served from memory, but never longer than the TTL or past an update.
+
+use actix_web::rt::time::sleep;
+use std::sync::Arc;
+use std::time::Duration;
+
+use oped_back::core::user::repository::UserRepository;
+use oped_back::infrastructure::user::{
+    cache::CachedUserRepository, repository::MemoryUserRepository,
+};
+
+/// The cache and, to change users behind its back, the repository it wraps.
+fn cached(ttl: Duration, capacity: usize) -> (CachedUserRepository, Arc<MemoryUserRepository>) {
+    let inner = Arc::new(MemoryUserRepository::new());
+
+    (
+        CachedUserRepository::new(inner.clone(), ttl, capacity),
+        inner,
+    )
+}
+
+async fn insert(repository: &dyn UserRepository, login: &str) -> i32 {
+    repository
+        .insert(login.to_owned(), "hash".to_owned(), "salt".to_owned())
+        .await
+        .expect("inserted")
+}
+
+/// Bans `id` in `repository`.
+async fn ban(repository: &dyn UserRepository, id: i32) {
+    let mut user = repository.select_one_by_id(id).await.expect("found");
+
+    user.set_banned(true);
+
+    repository.update(user).await.expect("updated");
+}
+
+async fn is_banned(repository: &dyn UserRepository, id: i32) -> bool {
+    repository
+        .select_one_by_id(id)
+        .await
+        .expect("found")
+        .is_banned()
+}
+
+#[actix_web::test]
+async fn serves_cached_user_until_it_expires() {
+    let (cache, inner) = cached(Duration::from_millis(100), 10);
+
+    let id = insert(&cache, "alice").await;
+
+    assert!(!is_banned(&cache, id).await);
+
+    ban(inner.as_ref(), id).await;
+
+    assert!(!is_banned(&cache, id).await);
+
+    sleep(Duration::from_millis(150)).await;
+
+    assert!(is_banned(&cache, id).await);
+}
+
+#[actix_web::test]
+async fn shares_entries_between_id_and_login() {
+    let (cache, inner) = cached(Duration::from_secs(60), 10);
+
+    let id = insert(&cache, "alice").await;
+
+    cache
+        .select_one_by_login("ALICE".to_owned())
+        .await
+        .expect("found");
+
+    ban(inner.as_ref(), id).await;
+
+    assert!(!is_banned(&cache, id).await);
+}
+
+#[actix_web::test]
+async fn evicts_user_on_update() {
+    let (cache, _) = cached(Duration::from_secs(60), 10);
+
+    let id = insert(&cache, "alice").await;
+
+    assert!(!is_banned(&cache, id).await);
+
+    ban(&cache, id).await;
+
+    assert!(is_banned(&cache, id).await);
+
+    let user = cache
+        .select_one_by_login("alice".to_owned())
+        .await
+        .expect("found");
+
+    assert!(user.is_banned());
+}
+
+#[actix_web::test]
+async fn holds_at_most_capacity_users() {
+    let (cache, inner) = cached(Duration::from_secs(60), 2);
+
+    let mut ids = vec![];
+
+    for login in ["alice", "bob", "carol"] {
+        let id = insert(&cache, login).await;
+
+        assert!(!is_banned(&cache, id).await);
+
+        ids.push(id);
+    }
+
+    for id in &ids {
+        ban(inner.as_ref(), *id).await;
+    }
+
+    // `alice` made room for `carol`; `bob` and `carol` are still cached.
+    assert!(is_banned(&cache, ids[0]).await);
+    assert!(!is_banned(&cache, ids[2]).await);
+}
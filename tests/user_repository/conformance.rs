@@ -0,0 +1,314 @@
+//! Behaviour every `UserRepository` must share, whatever it stores users in.
+//!
+//! A backend opts in with one `user_repository_conformance!` line in
+//! `main.rs`, naming it and giving a closure that returns an empty repository;
+//! the closure is called once per test.
+
+use chrono::Utc;
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+use actix_web::rt::Runtime;
+use oped_back::core::user::{
+    models::{PasswordAlgorithm, SortOrder, User, UserQuery, UserRecord, UserSortField},
+    repository::{
+        UserRepository, UserRepositoryInsertError, UserRepositorySelectOneError,
+        UserRepositoryUpdateError,
+    },
+};
+
+async fn insert(repository: &dyn UserRepository, login: &str) -> i32 {
+    repository
+        .insert(login.to_owned(), "hash".to_owned(), "salt".to_owned())
+        .await
+        .unwrap_or_else(|error| panic!("inserting `{}` failed: {:?}", login, error))
+}
+
+fn record(login: &str) -> UserRecord {
+    UserRecord::new(
+        login.to_owned(),
+        PasswordAlgorithm::Sha256,
+        "hash".to_owned(),
+        "salt".to_owned(),
+        vec!["admin".to_owned()],
+        Utc::now(),
+        false,
+    )
+}
+
+async fn count(repository: &dyn UserRepository) -> usize {
+    let query = UserQuery::new(None, UserSortField::Id, SortOrder::Asc, 0, usize::MAX);
+
+    repository
+        .select_many(query)
+        .await
+        .expect("listing users succeeds")
+        .get_total()
+}
+
+pub async fn selects_inserted_user(repository: Arc<dyn UserRepository>) {
+    let id = insert(repository.as_ref(), "alice").await;
+
+    let by_id = repository.select_one_by_id(id).await.expect("found by id");
+
+    assert_eq!(by_id.get_id(), id);
+    assert_eq!(by_id.clone_login(), "alice");
+    assert_eq!(by_id.clone_hash(), "hash");
+    assert_eq!(by_id.clone_salt(), "salt");
+
+    let by_login = repository
+        .select_one_by_login("alice".to_owned())
+        .await
+        .expect("found by login");
+
+    assert_eq!(by_login.get_id(), id);
+}
+
+pub async fn selects_login_case_insensitively(repository: Arc<dyn UserRepository>) {
+    let id = insert(repository.as_ref(), "Alice").await;
+
+    for login in ["alice", "ALICE", "аlice"] {
+        let user = repository
+            .select_one_by_login(login.to_owned())
+            .await
+            .unwrap_or_else(|error| panic!("`{}` not found: {:?}", login, error));
+
+        assert_eq!(user.get_id(), id);
+        assert_eq!(user.clone_login(), "Alice");
+    }
+}
+
+pub async fn rejects_taken_login(repository: Arc<dyn UserRepository>) {
+    insert(repository.as_ref(), "alice").await;
+
+    // "аlice" starts with a Cyrillic "а".
+    for login in ["alice", "ALICE", "аlice"] {
+        let result = repository
+            .insert(login.to_owned(), "hash".to_owned(), "salt".to_owned())
+            .await;
+
+        assert!(
+            matches!(result, Err(UserRepositoryInsertError::LoginAlreadyUsed)),
+            "`{}` was not rejected: {:?}",
+            login,
+            result
+        );
+    }
+
+    let result = repository.insert_record(record("Alice")).await;
+
+    assert!(matches!(
+        result,
+        Err(UserRepositoryInsertError::LoginAlreadyUsed)
+    ));
+
+    assert_eq!(count(repository.as_ref()).await, 1);
+}
+
+pub async fn assigns_increasing_ids(repository: Arc<dyn UserRepository>) {
+    let mut last = insert(repository.as_ref(), "user0").await;
+
+    for index in 1..20 {
+        let id = if index % 2 == 0 {
+            insert(repository.as_ref(), &format!("user{}", index)).await
+        } else {
+            repository
+                .insert_record(record(&format!("user{}", index)))
+                .await
+                .expect("record inserted")
+        };
+
+        assert!(id > last, "id {} assigned after {}", id, last);
+
+        last = id;
+    }
+
+    // A rejected insert must not hand its id to anybody later.
+    repository
+        .insert("user0".to_owned(), "hash".to_owned(), "salt".to_owned())
+        .await
+        .expect_err("login is taken");
+
+    assert!(insert(repository.as_ref(), "user20").await > last);
+}
+
+pub async fn reports_missing_users(repository: Arc<dyn UserRepository>) {
+    let id = insert(repository.as_ref(), "alice").await;
+
+    let by_id = repository.select_one_by_id(id + 1).await;
+
+    assert!(matches!(by_id, Err(UserRepositorySelectOneError::NotFound)));
+
+    let by_login = repository.select_one_by_login("bob".to_owned()).await;
+
+    assert!(matches!(
+        by_login,
+        Err(UserRepositorySelectOneError::NotFound)
+    ));
+
+    let missing = User::new(
+        id + 1,
+        "bob".to_owned(),
+        "hash".to_owned(),
+        "salt".to_owned(),
+        Utc::now(),
+    );
+
+    let update = repository.update(missing).await;
+
+    assert!(matches!(update, Err(UserRepositoryUpdateError::NotFound)));
+}
+
+pub async fn updates_stored_user(repository: Arc<dyn UserRepository>) {
+    let id = insert(repository.as_ref(), "alice").await;
+
+    let mut user = repository.select_one_by_id(id).await.expect("found by id");
+
+    user.set_banned(true);
+    user.grant_role("admin".to_owned());
+    user.set_password(
+        PasswordAlgorithm::Sha256,
+        "other".to_owned(),
+        "pepper".to_owned(),
+    );
+
+    repository.update(user).await.expect("updated");
+
+    let user = repository
+        .select_one_by_login("alice".to_owned())
+        .await
+        .expect("found by login");
+
+    assert!(user.is_banned());
+    assert!(user.has_role("admin"));
+    assert_eq!(user.clone_hash(), "other");
+    assert_eq!(user.clone_salt(), "pepper");
+    assert_eq!(count(repository.as_ref()).await, 1);
+}
+
+/// Races `threads` inserts, each on its own thread and runtime, released at
+/// once; returns what every one of them got.
+fn race<F>(
+    repository: &Arc<dyn UserRepository>,
+    threads: usize,
+    login: F,
+) -> Vec<Result<i32, UserRepositoryInsertError>>
+where
+    F: Fn(usize) -> String,
+{
+    let barrier = Arc::new(Barrier::new(threads));
+
+    let handles: Vec<_> = (0..threads)
+        .map(|index| {
+            let repository = repository.clone();
+            let barrier = barrier.clone();
+            let login = login(index);
+
+            thread::spawn(move || {
+                let runtime = Runtime::new().expect("runtime starts");
+
+                barrier.wait();
+
+                runtime.block_on(repository.insert(login, "hash".to_owned(), "salt".to_owned()))
+            })
+        })
+        .collect();
+
+    handles
+        .into_iter()
+        .map(|handle| handle.join().expect("insert does not panic"))
+        .collect()
+}
+
+pub fn concurrent_inserts_of_one_login_yield_one_user(repository: Arc<dyn UserRepository>) {
+    // Spellings that share one login key.
+    let spellings = ["alice", "Alice", "ALICE", "аlice"];
+
+    let results = race(&repository, 16, |index| {
+        spellings[index % spellings.len()].to_owned()
+    });
+
+    let succeeded = results.iter().filter(|result| result.is_ok()).count();
+
+    assert_eq!(succeeded, 1, "{:?}", results);
+
+    for result in results.iter().filter(|result| result.is_err()) {
+        assert!(
+            matches!(result, Err(UserRepositoryInsertError::LoginAlreadyUsed)),
+            "{:?}",
+            result
+        );
+    }
+
+    let runtime = Runtime::new().expect("runtime starts");
+
+    assert_eq!(runtime.block_on(count(repository.as_ref())), 1);
+}
+
+pub fn concurrent_inserts_get_distinct_ids(repository: Arc<dyn UserRepository>) {
+    let results = race(&repository, 16, |index| format!("user{}", index));
+
+    let mut ids: Vec<i32> = results
+        .into_iter()
+        .map(|result| result.expect("logins are distinct"))
+        .collect();
+
+    ids.sort_unstable();
+    ids.dedup();
+
+    assert_eq!(ids.len(), 16);
+}
+
+/// Expands to a module of tests running the whole suite against the
+/// repositories `$build` returns.
+macro_rules! user_repository_conformance {
+    ($backend:ident, $build:expr) => {
+        mod $backend {
+            use super::*;
+
+            fn repository() -> std::sync::Arc<dyn UserRepository> {
+                ($build)()
+            }
+
+            #[actix_web::test]
+            async fn selects_inserted_user() {
+                conformance::selects_inserted_user(repository()).await;
+            }
+
+            #[actix_web::test]
+            async fn selects_login_case_insensitively() {
+                conformance::selects_login_case_insensitively(repository()).await;
+            }
+
+            #[actix_web::test]
+            async fn rejects_taken_login() {
+                conformance::rejects_taken_login(repository()).await;
+            }
+
+            #[actix_web::test]
+            async fn assigns_increasing_ids() {
+                conformance::assigns_increasing_ids(repository()).await;
+            }
+
+            #[actix_web::test]
+            async fn reports_missing_users() {
+                conformance::reports_missing_users(repository()).await;
+            }
+
+            #[actix_web::test]
+            async fn updates_stored_user() {
+                conformance::updates_stored_user(repository()).await;
+            }
+
+            #[test]
+            fn concurrent_inserts_of_one_login_yield_one_user() {
+                conformance::concurrent_inserts_of_one_login_yield_one_user(repository());
+            }
+
+            #[test]
+            fn concurrent_inserts_get_distinct_ids() {
+                conformance::concurrent_inserts_get_distinct_ids(repository());
+            }
+        }
+    };
+}
@@ -0,0 +1,77 @@
+#[macro_use]
+mod conformance;
+mod cache;
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use oped_back::core::user::repository::UserRepository;
+use oped_back::infrastructure::user::{
+    cache::CachedUserRepository, repository::MemoryUserRepository,
+};
+
+/// A fresh data directory under Cargo's scratch space for integration tests.
+fn data_dir() -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!(
+        "user_repository-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    std::fs::remove_dir_all(&dir).ok();
+
+    dir
+}
+
+user_repository_conformance!(memory, || Arc::new(MemoryUserRepository::new()));
+
+user_repository_conformance!(memory_cached, || Arc::new(CachedUserRepository::new(
+    Arc::new(MemoryUserRepository::new()),
+    Duration::from_secs(60),
+    1_000,
+)));
+
+user_repository_conformance!(memory_persisted, || Arc::new(
+    MemoryUserRepository::open(&data_dir()).expect("data directory opens")
+));
+
+#[actix_web::test]
+async fn memory_persisted_recovers_users_after_reopen() {
+    let dir = data_dir();
+
+    let repository = MemoryUserRepository::open(&dir).expect("data directory opens");
+
+    let alice = repository
+        .insert("alice".to_owned(), "hash".to_owned(), "salt".to_owned())
+        .await
+        .expect("inserted");
+
+    repository.flush().await.expect("flushed");
+
+    let bob = repository
+        .insert("bob".to_owned(), "hash".to_owned(), "salt".to_owned())
+        .await
+        .expect("inserted");
+
+    // Bob is only in the log; dropping releases the directory lock.
+    drop(repository);
+
+    let repository = MemoryUserRepository::open(&dir).expect("data directory reopens");
+
+    for (id, login) in [(alice, "alice"), (bob, "bob")] {
+        let user = repository.select_one_by_id(id).await.expect("recovered");
+
+        assert_eq!(user.clone_login(), login);
+    }
+
+    let carol = repository
+        .insert("carol".to_owned(), "hash".to_owned(), "salt".to_owned())
+        .await
+        .expect("inserted");
+
+    assert!(carol > bob);
+}